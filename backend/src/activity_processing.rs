@@ -0,0 +1,247 @@
+use crate::config::StatsWindow;
+use crate::organization_processing::OrganizationKind;
+use crate::stats_processing;
+use crate::stats_processing::push_window_filter;
+use schemars::JsonSchema;
+use sea_orm::{DatabaseConnection, DbBackend, DbErr, FromQueryResult, Statement, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The three broad timezones EVE players talk about, as blocks of UTC hours
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq)]
+pub enum TimezoneBlock {
+    /// 00:00 to 07:59 UTC
+    US,
+    /// 08:00 to 15:59 UTC
+    AU,
+    /// 16:00 to 23:59 UTC
+    EU,
+}
+
+impl TimezoneBlock {
+    fn from_hour(hour: usize) -> TimezoneBlock {
+        match hour {
+            0..=7 => TimezoneBlock::US,
+            8..=15 => TimezoneBlock::AU,
+            _ => TimezoneBlock::EU,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct TimezoneEstimate {
+    pub block: TimezoneBlock,
+    /// Share of all activity that falls inside `block`, from 0 to 1
+    pub confidence: f64,
+}
+
+/// When a pilot or organization shows up on killmails. Hours are UTC and days
+/// start on Monday.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct ActivityProfile {
+    /// Killmail counts indexed by `[day_of_week][hour_of_day]`
+    pub hour_of_week: Vec<Vec<usize>>,
+    pub hour_of_day: Vec<usize>,
+    pub day_of_week: Vec<usize>,
+    pub total: usize,
+    pub timezone: Option<TimezoneEstimate>,
+}
+
+impl Default for ActivityProfile {
+    fn default() -> Self {
+        ActivityProfile {
+            hour_of_week: vec![vec![0; 24]; 7],
+            hour_of_day: vec![0; 24],
+            day_of_week: vec![0; 7],
+            total: 0,
+            timezone: None,
+        }
+    }
+}
+
+impl ActivityProfile {
+    fn add(&mut self, day: usize, hour: usize, count: usize) {
+        self.hour_of_week[day][hour] += count;
+        self.hour_of_day[hour] += count;
+        self.day_of_week[day] += count;
+        self.total += count;
+    }
+
+    fn estimate_timezone(&mut self) {
+        if self.total == 0 {
+            self.timezone = None;
+            return;
+        }
+        let mut block_counts = [
+            (TimezoneBlock::US, 0),
+            (TimezoneBlock::AU, 0),
+            (TimezoneBlock::EU, 0),
+        ];
+        for (hour, count) in self.hour_of_day.iter().enumerate() {
+            let block = TimezoneBlock::from_hour(hour);
+            if let Some(entry) = block_counts.iter_mut().find(|(b, _)| *b == block) {
+                entry.1 += count;
+            }
+        }
+        let (block, count) = block_counts
+            .iter()
+            .max_by_key(|(_, count)| *count)
+            .cloned()
+            .unwrap();
+        self.timezone = Some(TimezoneEstimate {
+            block,
+            confidence: count as f64 / self.total as f64,
+        });
+    }
+}
+
+#[derive(Debug, FromQueryResult)]
+struct ActivityBucket {
+    weekday: i32,
+    hour: i32,
+    count: i64,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct CharacterActivityBucket {
+    character_id: u64,
+    weekday: i32,
+    hour: i32,
+    count: i64,
+}
+
+/// Build an activity profile for each character from the killmails they were
+/// on inside `window`, aggregated in the database
+pub async fn get_character_activities(
+    db: &DatabaseConnection,
+    character_ids: &[u64],
+    window: &StatsWindow,
+) -> Result<HashMap<u64, ActivityProfile>, DbErr> {
+    if character_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let mut values: Vec<Value> = Vec::new();
+    let mut sql = format!(
+        r#"SELECT c.character_id AS character_id, WEEKDAY(k.killmail_time) AS weekday,
+            HOUR(k.killmail_time) AS hour, COUNT(*) AS count
+        FROM ({}) c
+        JOIN killmails k ON k.killmail_id = c.killmail_id
+        WHERE 1 = 1"#,
+        stats_processing::character_killmails_sql(&mut values, character_ids)
+    );
+    push_window_filter(&mut sql, &mut values, window);
+    sql.push_str(" GROUP BY c.character_id, weekday, hour");
+    let mut profiles: HashMap<u64, ActivityProfile> = character_ids
+        .iter()
+        .map(|character_id| (*character_id, ActivityProfile::default()))
+        .collect();
+    for bucket in CharacterActivityBucket::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::MySql,
+        &sql,
+        values,
+    ))
+    .all(db)
+    .await?
+    {
+        if let Some(profile) = profiles.get_mut(&bucket.character_id) {
+            profile.add(
+                bucket.weekday as usize,
+                bucket.hour as usize,
+                bucket.count as usize,
+            );
+        }
+    }
+    for profile in profiles.values_mut() {
+        profile.estimate_timezone();
+    }
+    Ok(profiles)
+}
+
+/// Build an activity profile for a corporation or alliance from every killmail
+/// its members appear on, aggregated in the database
+pub async fn get_organization_activity(
+    db: &DatabaseConnection,
+    kind: OrganizationKind,
+    organization_id: u64,
+    window: &StatsWindow,
+) -> Result<ActivityProfile, DbErr> {
+    let column = kind.column_name();
+    let mut sql = format!(
+        r#"SELECT WEEKDAY(k.killmail_time) AS weekday, HOUR(k.killmail_time) AS hour, COUNT(*) AS count
+        FROM killmails k
+        WHERE (k.killmail_id IN (SELECT killmail_id FROM attackers WHERE {column} = ?)
+            OR k.killmail_id IN (SELECT killmail_id FROM victims WHERE {column} = ?))"#,
+        column = column
+    );
+    let mut values: Vec<Value> = vec![organization_id.into(), organization_id.into()];
+    if let Some(start_time) = window.start_time() {
+        sql.push_str(" AND k.killmail_time >= ?");
+        values.push(start_time.into());
+    }
+    sql.push_str(" GROUP BY weekday, hour");
+    let buckets = ActivityBucket::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::MySql,
+        &sql,
+        values,
+    ))
+    .all(db)
+    .await?;
+    let mut profile = ActivityProfile::default();
+    for bucket in buckets {
+        profile.add(
+            bucket.weekday as usize,
+            bucket.hour as usize,
+            bucket.count as usize,
+        );
+    }
+    profile.estimate_timezone();
+    Ok(profile)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hours_fall_in_their_blocks() {
+        for (hour, block) in [
+            (0, TimezoneBlock::US),
+            (7, TimezoneBlock::US),
+            (8, TimezoneBlock::AU),
+            (15, TimezoneBlock::AU),
+            (16, TimezoneBlock::EU),
+            (23, TimezoneBlock::EU),
+        ] {
+            assert_eq!(TimezoneBlock::from_hour(hour), block, "hour {}", hour);
+        }
+    }
+
+    #[test]
+    fn add_counts_every_view() {
+        let mut profile = ActivityProfile::default();
+        profile.add(0, 20, 3);
+        profile.add(6, 20, 2);
+        profile.add(6, 3, 1);
+        assert_eq!(profile.hour_of_week[0][20], 3);
+        assert_eq!(profile.hour_of_week[6][20], 2);
+        assert_eq!(profile.hour_of_day[20], 5);
+        assert_eq!(profile.day_of_week[6], 3);
+        assert_eq!(profile.total, 6);
+    }
+
+    #[test]
+    fn estimates_the_busiest_block() {
+        let mut profile = ActivityProfile::default();
+        profile.estimate_timezone();
+        assert!(profile.timezone.is_none());
+
+        profile.add(2, 7, 1);
+        profile.add(2, 8, 3);
+        profile.add(3, 15, 3);
+        profile.add(4, 16, 1);
+        profile.estimate_timezone();
+        let timezone = profile.timezone.unwrap();
+        assert_eq!(timezone.block, TimezoneBlock::AU);
+        assert!((timezone.confidence - 0.75).abs() < f64::EPSILON);
+    }
+}
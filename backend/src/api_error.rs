@@ -0,0 +1,227 @@
+use crate::database::JagerDatabaseError;
+use crate::esi::EsiError;
+use crate::killmail_processing::ProcessingError;
+use rocket::http::Status;
+use rocket::request::Request;
+use rocket::response::status::Custom;
+use rocket::response::{self, Responder};
+use rocket::serde::json::Json;
+use rocket_okapi::gen::OpenApiGenerator;
+use rocket_okapi::okapi::openapi3::Responses;
+use rocket_okapi::response::OpenApiResponderInner;
+use rocket_okapi::util::add_schema_response;
+use schemars::JsonSchema;
+use sea_orm::DbErr;
+use serde::{Deserialize, Serialize};
+
+/// The body of every error response
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ErrorMessage {
+    /// Stable identifier for the kind of error, see `ApiError::code`
+    pub code: String,
+    pub message: String,
+}
+
+/// Everything a handler can fail with. Each variant has its own status code
+/// so clients can tell a pilot with no history from a server that's broken.
+#[derive(Debug)]
+pub enum ApiError {
+    /// Something in the request is invalid, e.g. an unknown window
+    BadRequest(String),
+    NotFound(String),
+    /// A name matched more than one character or organization
+    Ambiguous(String),
+    /// ESI is limiting our requests
+    RateLimited,
+    /// ESI failed or sent something we couldn't read
+    Upstream(String),
+    /// The database can't be reached. Without Redis requests skip the cache.
+    Unavailable(String),
+    Internal(String),
+}
+
+impl ApiError {
+    pub fn status(&self) -> Status {
+        match self {
+            ApiError::BadRequest(_) => Status::BadRequest,
+            ApiError::NotFound(_) => Status::NotFound,
+            ApiError::Ambiguous(_) => Status::Conflict,
+            ApiError::RateLimited => Status::TooManyRequests,
+            ApiError::Upstream(_) => Status::BadGateway,
+            ApiError::Unavailable(_) => Status::ServiceUnavailable,
+            ApiError::Internal(_) => Status::InternalServerError,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::BadRequest(_) => "bad_request",
+            ApiError::NotFound(_) => "not_found",
+            ApiError::Ambiguous(_) => "ambiguous_name",
+            ApiError::RateLimited => "rate_limited",
+            ApiError::Upstream(_) => "upstream_error",
+            ApiError::Unavailable(_) => "service_unavailable",
+            ApiError::Internal(_) => "internal_error",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            ApiError::RateLimited => "ESI is rate limiting requests, try again later".to_string(),
+            ApiError::BadRequest(message)
+            | ApiError::NotFound(message)
+            | ApiError::Ambiguous(message)
+            | ApiError::Upstream(message)
+            | ApiError::Unavailable(message)
+            | ApiError::Internal(message) => message.clone(),
+        }
+    }
+
+    /// `kind` is what was looked for, e.g. "character"
+    pub fn not_found(kind: &str, name: &str) -> ApiError {
+        ApiError::NotFound(format!("No {} named {} has any history", kind, name))
+    }
+
+    pub fn to_error_message(&self) -> ErrorMessage {
+        ErrorMessage {
+            code: self.code().to_string(),
+            message: self.message(),
+        }
+    }
+}
+
+/// Messages of the sqlx errors sea-orm passes on as query or exec errors
+/// when the database can't be reached, rather than when a query is wrong
+const UNAVAILABLE_DB_ERRORS: [&str; 7] = [
+    "pool timed out while waiting for an open connection",
+    "attempted to acquire a connection on a closed pool",
+    "attempted to communicate with a crashed background worker",
+    "error communicating with",
+    "error occurred while attempting to establish a TLS connection",
+    "server has gone away",
+    "Too many connections",
+];
+
+/// Whether a database error means the database is down or overloaded. Pool
+/// timeouts and dropped connections only show up as query or exec errors.
+fn is_db_unavailable(err: &DbErr) -> bool {
+    match err {
+        DbErr::Conn(_) => true,
+        DbErr::Query(message) | DbErr::Exec(message) => UNAVAILABLE_DB_ERRORS
+            .iter()
+            .any(|unavailable| message.contains(unavailable)),
+        _ => false,
+    }
+}
+
+impl From<DbErr> for ApiError {
+    fn from(err: DbErr) -> ApiError {
+        error!("Database error: {:?}", err);
+        if is_db_unavailable(&err) {
+            ApiError::Unavailable("The database is unavailable".to_string())
+        } else {
+            ApiError::Internal("A database query failed".to_string())
+        }
+    }
+}
+
+impl From<EsiError> for ApiError {
+    fn from(err: EsiError) -> ApiError {
+        if err.is_rate_limited() {
+            warn!("Rate limited by ESI: {:?}", err);
+            return ApiError::RateLimited;
+        }
+        error!("ESI error: {:?}", err);
+        ApiError::Upstream("ESI request failed".to_string())
+    }
+}
+
+impl From<JagerDatabaseError> for ApiError {
+    fn from(err: JagerDatabaseError) -> ApiError {
+        match err {
+            JagerDatabaseError::DBError(e) => e.into(),
+            e => {
+                error!("Database error: {:?}", e);
+                ApiError::Internal("Unexpected data in the database".to_string())
+            }
+        }
+    }
+}
+
+impl From<ProcessingError> for ApiError {
+    fn from(err: ProcessingError) -> ApiError {
+        match err {
+            ProcessingError::ESIError(e) => e.into(),
+            ProcessingError::DBError(e) => e.into(),
+            ProcessingError::JagerDatabaseError(e) => e.into(),
+            ProcessingError::AmbiguousCharacterName(character_ids) => ApiError::Ambiguous(format!(
+                "The name is currently held by more than one character: {:?}",
+                character_ids
+            )),
+            ProcessingError::AmbiguousOrganizationName(organization_ids) => {
+                ApiError::Ambiguous(format!(
+                    "The name matches more than one organization: {:?}",
+                    organization_ids
+                ))
+            }
+        }
+    }
+}
+
+impl<'r> Responder<'r, 'static> for ApiError {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        Custom(self.status(), Json(self.to_error_message())).respond_to(req)
+    }
+}
+
+impl OpenApiResponderInner for ApiError {
+    fn responses(gen: &mut OpenApiGenerator) -> rocket_okapi::Result<Responses> {
+        let mut responses = Responses::default();
+        for status in [400, 404, 409, 429, 500, 502, 503] {
+            add_schema_response(
+                &mut responses,
+                status,
+                "application/json",
+                gen.json_schema::<ErrorMessage>(),
+            )?;
+        }
+        Ok(responses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn database_outages_are_unavailable() {
+        for err in [
+            DbErr::Conn("connection refused".to_string()),
+            DbErr::Query("pool timed out while waiting for an open connection".to_string()),
+            DbErr::Exec("attempted to acquire a connection on a closed pool".to_string()),
+            DbErr::Query(
+                "error communicating with the server: Connection reset by peer (os error 104)"
+                    .to_string(),
+            ),
+            DbErr::Exec(
+                "error returned from database: 1040 (08004): Too many connections".to_string(),
+            ),
+        ] {
+            assert_eq!(ApiError::from(err).status(), Status::ServiceUnavailable);
+        }
+    }
+
+    #[test]
+    fn failed_queries_are_internal() {
+        for err in [
+            DbErr::Query(
+                "error returned from database: 1054 (42S22): Unknown column 'x'".to_string(),
+            ),
+            DbErr::Exec("error returned from database: 1062 (23000): Duplicate entry".to_string()),
+            DbErr::Type("expected u64".to_string()),
+            DbErr::RecordNotFound("character".to_string()),
+        ] {
+            assert_eq!(ApiError::from(err).status(), Status::InternalServerError);
+        }
+    }
+}
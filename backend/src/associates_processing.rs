@@ -0,0 +1,148 @@
+use crate::config;
+use crate::config::StatsWindow;
+use crate::entity::prelude::*;
+use crate::entity::*;
+use crate::killmail_processing::ProcessingError;
+use crate::stats_processing;
+use chrono::NaiveDateTime;
+use schemars::JsonSchema;
+use sea_orm::prelude::*;
+use sea_orm::{DatabaseConnection, DbBackend, DbErr, FromQueryResult, Statement, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A character, corporation or alliance seen fighting alongside a pilot
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct Associate {
+    pub id: u64,
+    pub name: Option<String>,
+    /// Killmails both appear on as attackers
+    pub count: usize,
+    pub last_seen: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+pub struct Associates {
+    pub characters: Vec<Associate>,
+    pub corporations: Vec<Associate>,
+    pub alliances: Vec<Associate>,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct CoOccurrence {
+    id: u64,
+    count: i64,
+    last_seen: NaiveDateTime,
+}
+
+/// Who most often shows up as a fellow attacker on `character_id`'s kills,
+/// grouped by the attackers' `column`
+async fn get_co_occurrences(
+    db: &DatabaseConnection,
+    character_id: u64,
+    window: &StatsWindow,
+    column: &str,
+) -> Result<Vec<CoOccurrence>, DbErr> {
+    let mut sql = format!(
+        r#"SELECT o.{column} AS id, COUNT(DISTINCT o.killmail_id) AS count,
+            MAX(k.killmail_time) AS last_seen
+        FROM attackers a
+        JOIN attackers o ON o.killmail_id = a.killmail_id
+        JOIN killmails k ON k.killmail_id = a.killmail_id
+        WHERE a.character_id = ?
+            AND o.{column} IS NOT NULL
+            AND (o.character_id IS NULL OR o.character_id <> a.character_id)"#,
+        column = column
+    );
+    let mut values: Vec<Value> = vec![character_id.into()];
+    if let Some(start_time) = window.start_time() {
+        sql.push_str(" AND k.killmail_time >= ?");
+        values.push(start_time.into());
+    }
+    sql.push_str(&format!(
+        " GROUP BY o.{} ORDER BY count DESC, last_seen DESC LIMIT ?",
+        column
+    ));
+    values.push((config::get().stats.top_entries as u64).into());
+    CoOccurrence::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::MySql,
+        &sql,
+        values,
+    ))
+    .all(db)
+    .await
+}
+
+fn to_associates(
+    co_occurrences: Vec<CoOccurrence>,
+    names: &HashMap<u64, String>,
+) -> Vec<Associate> {
+    co_occurrences
+        .into_iter()
+        .map(|co_occurrence| Associate {
+            id: co_occurrence.id,
+            name: names.get(&co_occurrence.id).cloned(),
+            count: co_occurrence.count as usize,
+            last_seen: co_occurrence.last_seen,
+        })
+        .collect()
+}
+
+fn get_ids(co_occurrences: &[CoOccurrence]) -> Vec<u64> {
+    co_occurrences
+        .iter()
+        .map(|co_occurrence| co_occurrence.id)
+        .collect()
+}
+
+/// The characters, corporations and alliances that most often fly with a
+/// pilot. Only kills are considered, the attackers on a pilot's losses are
+/// the other side.
+pub async fn get_associates(
+    db: &DatabaseConnection,
+    character_id: u64,
+    window: &StatsWindow,
+) -> Result<Associates, DbErr> {
+    let characters = get_co_occurrences(db, character_id, window, "character_id").await?;
+    let corporations = get_co_occurrences(db, character_id, window, "corporation_id").await?;
+    let alliances = get_co_occurrences(db, character_id, window, "alliance_id").await?;
+    let character_names: HashMap<u64, String> = CharacterPublicInfo::find()
+        .filter(character_public_info::Column::CharacterId.is_in(get_ids(&characters)))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|character| (character.character_id, character.character_name))
+        .collect();
+    let corporation_names: HashMap<u64, String> = Corporations::find()
+        .filter(corporations::Column::CorporationId.is_in(get_ids(&corporations)))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|corporation| (corporation.corporation_id, corporation.name))
+        .collect();
+    let alliance_names: HashMap<u64, String> = Alliances::find()
+        .filter(alliances::Column::AllianceId.is_in(get_ids(&alliances)))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|alliance| (alliance.alliance_id, alliance.name))
+        .collect();
+    Ok(Associates {
+        characters: to_associates(characters, &character_names),
+        corporations: to_associates(corporations, &corporation_names),
+        alliances: to_associates(alliances, &alliance_names),
+    })
+}
+
+pub async fn get_character_associates(
+    db: &DatabaseConnection,
+    name: String,
+    window: &StatsWindow,
+) -> Result<Option<Associates>, ProcessingError> {
+    match stats_processing::get_or_update_character_public_info(db, name).await? {
+        Some(char_info) => Ok(Some(
+            get_associates(db, char_info.character_id, window).await?,
+        )),
+        None => Ok(None),
+    }
+}
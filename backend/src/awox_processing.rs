@@ -0,0 +1,145 @@
+use crate::config::StatsWindow;
+use crate::stats_processing;
+use crate::stats_processing::{push_window_filter, KillLossRatio};
+use datamodels::esi_models::ESIKillmail;
+use sea_orm::{DatabaseConnection, DbBackend, DbErr, FromQueryResult, Statement, Value};
+use std::collections::HashMap;
+
+/// NPC corporations, which players end up in when they leave a player corp.
+/// Two pilots sharing one of these are not corp mates.
+const NPC_CORPORATION_IDS: std::ops::RangeInclusive<u64> = 1_000_000..=1_999_999;
+
+/// Whether an attacker and a victim are in the same player corporation or
+/// alliance. Attacking yourself doesn't count.
+fn shares_organization(
+    victim: (Option<u64>, Option<u64>, Option<u64>),
+    attacker: (Option<u64>, Option<u64>, Option<u64>),
+) -> bool {
+    let (victim_character, victim_corporation, victim_alliance) = victim;
+    let (attacker_character, attacker_corporation, attacker_alliance) = attacker;
+    if attacker_character.is_none() || attacker_character == victim_character {
+        return false;
+    }
+    let same_corporation = match (victim_corporation, attacker_corporation) {
+        (Some(victim_corporation), Some(attacker_corporation)) => {
+            victim_corporation == attacker_corporation
+                && !NPC_CORPORATION_IDS.contains(&victim_corporation)
+        }
+        _ => false,
+    };
+    let same_alliance = victim_alliance.is_some() && victim_alliance == attacker_alliance;
+    same_corporation || same_alliance
+}
+
+/// Whether a killmail is an awox, either because zKillboard says so or because
+/// a player attacker shares the victim's corporation or alliance
+pub fn is_awox_killmail(killmail: &ESIKillmail) -> bool {
+    if killmail.zkb.as_ref().is_some_and(|zkb| zkb.awox) {
+        return true;
+    }
+    let victim = &killmail.victim;
+    killmail.attackers.iter().any(|attacker| {
+        shares_organization(
+            (
+                victim.character_id,
+                victim.corporation_id,
+                victim.alliance_id,
+            ),
+            (
+                attacker.character_id,
+                attacker.corporation_id,
+                attacker.alliance_id,
+            ),
+        )
+    })
+}
+
+/// `shares_organization` as a SQL condition on attacker `a` and victim `v`
+fn friendly_fire_condition() -> String {
+    format!(
+        r#"a.character_id IS NOT NULL
+        AND NOT (a.character_id <=> v.character_id)
+        AND ((a.corporation_id = v.corporation_id
+                AND v.corporation_id NOT BETWEEN {npc_start} AND {npc_end})
+            OR (v.alliance_id IS NOT NULL AND a.alliance_id = v.alliance_id))"#,
+        npc_start = NPC_CORPORATION_IDS.start(),
+        npc_end = NPC_CORPORATION_IDS.end(),
+    )
+}
+
+#[derive(Debug, FromQueryResult)]
+struct AwoxCount {
+    character_id: u64,
+    count: i64,
+}
+
+async fn get_awox_count_map(
+    db: &DatabaseConnection,
+    sql: String,
+    values: Vec<Value>,
+) -> Result<HashMap<u64, usize>, DbErr> {
+    Ok(AwoxCount::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::MySql,
+        &sql,
+        values,
+    ))
+    .all(db)
+    .await?
+    .into_iter()
+    .map(|count| (count.character_id, count.count as usize))
+    .collect())
+}
+
+/// How often each character killed their own corp or alliance mates inside
+/// `window`, and how often they were killed by them. Both sides count a
+/// killmail as an awox when it's flagged as one or an attacker is friendly
+/// fire, so every awox loss shows up as an awox kill for its attackers.
+pub async fn get_awox_counts(
+    db: &DatabaseConnection,
+    character_ids: &[u64],
+    window: &StatsWindow,
+) -> Result<HashMap<u64, KillLossRatio>, DbErr> {
+    if character_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let mut values: Vec<Value> = Vec::new();
+    let mut sql = format!(
+        r#"SELECT a.character_id AS character_id, COUNT(DISTINCT a.killmail_id) AS count
+        FROM attackers a
+        JOIN victims v ON v.killmail_id = a.killmail_id
+        JOIN killmails k ON k.killmail_id = a.killmail_id
+        WHERE a.character_id IN ({}) AND (k.awox OR ({}))"#,
+        stats_processing::push_id_list(&mut values, character_ids),
+        friendly_fire_condition()
+    );
+    push_window_filter(&mut sql, &mut values, window);
+    sql.push_str(" GROUP BY a.character_id");
+    let kills = get_awox_count_map(db, sql, values).await?;
+    let mut values: Vec<Value> = Vec::new();
+    let mut sql = format!(
+        r#"SELECT v.character_id AS character_id, COUNT(*) AS count
+        FROM victims v
+        JOIN killmails k ON k.killmail_id = v.killmail_id
+        WHERE v.character_id IN ({})
+            AND (k.awox OR EXISTS (
+                SELECT 1 FROM attackers a WHERE a.killmail_id = v.killmail_id AND {}
+            ))"#,
+        stats_processing::push_id_list(&mut values, character_ids),
+        friendly_fire_condition()
+    );
+    push_window_filter(&mut sql, &mut values, window);
+    sql.push_str(" GROUP BY v.character_id");
+    let losses = get_awox_count_map(db, sql, values).await?;
+    Ok(character_ids
+        .iter()
+        .map(|character_id| {
+            (
+                *character_id,
+                KillLossRatio {
+                    kills: kills.get(character_id).copied().unwrap_or(0),
+                    losses: losses.get(character_id).copied().unwrap_or(0),
+                },
+            )
+        })
+        .collect())
+}
@@ -1,24 +1,118 @@
 #[macro_use]
 extern crate rocket;
+use backend::activity_processing;
+use backend::activity_processing::ActivityProfile;
+use backend::api_error::{ApiError, ErrorMessage};
+use backend::associates_processing;
+use backend::associates_processing::Associates;
+use backend::compare_processing;
+use backend::compare_processing::Comparison;
+use backend::config;
 use backend::database::Db;
+use backend::fleet_processing;
+use backend::fleet_processing::LocalAnalysis;
 use backend::jager_redis;
+use backend::killmail_detail_processing;
+use backend::killmail_detail_processing::{
+    CharacterKillmailFilter, CharacterKillmailPage, KillmailDetail,
+};
+use backend::killmail_search_processing;
+use backend::killmail_search_processing::{KillmailSearch, KillmailSearchResults};
+use backend::location_processing;
+use backend::location_processing::LocationProfile;
+use backend::name_processing;
+use backend::name_processing::NameLookup;
+use backend::organization_processing::OrganizationKind;
+use backend::organization_stats_processing;
+use backend::organization_stats_processing::OrganizationStats;
+use backend::search_processing;
+use backend::search_processing::{SearchQuery, SearchResults};
 use backend::stats_processing;
-use bb8_redis::bb8::Pool;
+use backend::stats_processing::CharacterStatsResult;
+use backend::timeseries_processing;
+use backend::timeseries_processing::{Timeseries, TimeseriesRange, TimeseriesSubject};
+use bb8_redis::bb8::{Pool, PooledConnection};
 use bb8_redis::RedisConnectionManager;
-use rocket::fairing::{AdHoc};
-use rocket::request::Request;
+use futures::stream::BoxStream;
+use futures::{Stream, StreamExt};
+use rocket::http::ContentType;
+use rocket::request::{self, FromRequest, Request};
+use rocket::response::stream::{stream, TextStream};
+use rocket::response::{self, Responder};
 use rocket::serde::json::Json;
 use rocket::State;
+use rocket_okapi::gen::OpenApiGenerator;
+use rocket_okapi::okapi::openapi3::{MediaType, Responses};
+use rocket_okapi::request::{OpenApiFromRequest, RequestHeaderInput};
+use rocket_okapi::response::OpenApiResponderInner;
+use rocket_okapi::util::add_content_response;
+use rocket_okapi::{openapi, openapi_get_routes};
+use schemars::JsonSchema;
+use sea_orm::DatabaseConnection;
 use sea_orm_rocket::Connection;
 use sea_orm_rocket::Database as SODatabase;
 use serde::Deserialize;
-use serde::Serialize;
+use std::marker::PhantomData;
 
-#[derive(Serialize, Debug)]
-pub struct ErrorMessage {
-    message: String,
+/// Names pasted from the local chat channel
+#[derive(Deserialize, JsonSchema, Debug)]
+pub struct LocalList {
+    names: Vec<String>,
 }
 
+/// The database connection as a request guard. sea-orm-rocket's `Connection`
+/// can't be documented by rocket_okapi, so handlers take this instead.
+pub struct DbConn<'r>(&'r DatabaseConnection);
+
+impl<'r> DbConn<'r> {
+    fn into_inner(self) -> &'r DatabaseConnection {
+        self.0
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for DbConn<'r> {
+    type Error = <Connection<'r, Db> as FromRequest<'r>>::Error;
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        Connection::<'r, Db>::from_request(req)
+            .await
+            .map(|conn| DbConn(conn.into_inner()))
+    }
+}
+
+impl<'r> OpenApiFromRequest<'r> for DbConn<'r> {
+    fn from_request_input(
+        _gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(RequestHeaderInput::None)
+    }
+}
+
+/// A stream of `T` serialized as JSON, one object per line
+pub struct NdjsonStream<T, S>(TextStream<S>, PhantomData<T>);
+
+impl<'r, T, S: Stream<Item = String> + Send + 'r> Responder<'r, 'r> for NdjsonStream<T, S> {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'r> {
+        (ContentType::new("application", "x-ndjson"), self.0).respond_to(req)
+    }
+}
+
+impl<T: JsonSchema, S> OpenApiResponderInner for NdjsonStream<T, S> {
+    fn responses(gen: &mut OpenApiGenerator) -> rocket_okapi::Result<Responses> {
+        let mut responses = Responses::default();
+        let media = MediaType {
+            schema: Some(gen.json_schema::<T>()),
+            ..Default::default()
+        };
+        add_content_response(&mut responses, 200, "application/x-ndjson", media)?;
+        Ok(responses)
+    }
+}
+
+#[openapi(tag = "Meta")]
 #[get("/")]
 fn index() -> &'static str {
     "Hello, world!"
@@ -26,63 +120,675 @@ fn index() -> &'static str {
 
 #[catch(404)]
 fn not_found(_req: &Request) -> Json<ErrorMessage> {
-    Json(ErrorMessage {
-        message: "The resource you were looking for doesn't exist".to_string(),
-    })
+    Json(
+        ApiError::NotFound("The resource you were looking for doesn't exist".to_string())
+            .to_error_message(),
+    )
+}
+
+/// A connection to the cache, or `None` if Redis can't be reached. Requests
+/// are then served from the database without caching, only the database being
+/// down makes the API unavailable.
+async fn get_cache(
+    redis_pool: &Pool<RedisConnectionManager>,
+) -> Option<PooledConnection<'_, RedisConnectionManager>> {
+    match redis_pool.get().await {
+        Ok(conn) => Some(conn),
+        Err(e) => {
+            warn!(
+                "Couldn't get a redis connection, skipping the cache: {:?}",
+                e
+            );
+            None
+        }
+    }
+}
+
+/// Look up the stats window named in the query string, falling back to the
+/// configured default
+fn resolve_window(window: Option<String>) -> Result<&'static config::StatsWindow, ApiError> {
+    let stats_config = &config::get().stats;
+    match window {
+        Some(window_name) => stats_config.get_window(&window_name).ok_or_else(|| {
+            let valid_windows: Vec<&str> = stats_config
+                .windows
+                .iter()
+                .map(|window| window.name.as_str())
+                .collect();
+            ApiError::BadRequest(format!(
+                "Unknown window {}, expected one of {}",
+                window_name,
+                valid_windows.join(", ")
+            ))
+        }),
+        None => Ok(stats_config.get_default_window()),
+    }
 }
 
-#[get("/character_stats/<character_name>")]
+#[openapi(tag = "Characters")]
+#[get("/character_stats/<character_name>?<window>")]
 async fn get_character_stats(
-    conn: Connection<'_, Db>,
+    conn: DbConn<'_>,
     redis_pool: &State<Pool<RedisConnectionManager>>,
     character_name: String,
-) -> Option<Json<stats_processing::CharacterStats>> {
+    window: Option<String>,
+) -> Result<Json<stats_processing::CharacterStats>, ApiError> {
+    let window = resolve_window(window)?;
     let db = conn.into_inner();
-    let mut redis_conn = redis_pool.clone().get().await.unwrap();
-    match jager_redis::check_cache_character_stats(&mut redis_conn, &character_name).await {
-        Some(stats) => Some(Json(stats)),
-        None => match stats_processing::get_character_stats(db, character_name.clone()).await {
-            Ok(stats) => match stats {
+    let mut redis_conn = get_cache(redis_pool).await;
+    if let Some(redis_conn) = redis_conn.as_mut() {
+        if let Some(stats) =
+            jager_redis::check_cache_character_stats(redis_conn, &character_name, &window.name)
+                .await
+        {
+            return Ok(Json(stats));
+        }
+    }
+    match stats_processing::get_character_stats(db, character_name.clone(), window).await? {
+        Some(stats) => {
+            if let Some(redis_conn) = redis_conn.as_mut() {
+                jager_redis::cache_character_stats(
+                    redis_conn,
+                    &character_name,
+                    &window.name,
+                    &stats,
+                )
+                .await;
+            }
+            Ok(Json(stats))
+        }
+        None => Err(ApiError::not_found("character", &character_name)),
+    }
+}
+
+/// One JSON object per line, written as each name is resolved
+fn ndjson_line(result: &CharacterStatsResult) -> String {
+    match serde_json::to_string(result) {
+        Ok(line) => line + "\n",
+        Err(e) => {
+            error!("Couldn't serialize stats for {}: {:?}", result.name, e);
+            let fallback = CharacterStatsResult::error(
+                result.name.clone(),
+                "Failed to serialize stats".to_string(),
+            );
+            serde_json::to_string(&fallback).unwrap_or_default() + "\n"
+        }
+    }
+}
+
+/// Stats for many names at once, streamed back as NDJSON. Cached stats come
+/// first, then names we don't know, then the rest once they've been computed
+/// together. The stream owns its database and cache pools, since
+/// rocket_okapi can't document a handler generic over the request lifetime.
+#[openapi(tag = "Characters")]
+#[post("/character_stats?<window>", data = "<names>")]
+async fn get_many_character_stats(
+    conn: DbConn<'_>,
+    redis_pool: &State<Pool<RedisConnectionManager>>,
+    names: Json<Vec<String>>,
+    window: Option<String>,
+) -> Result<NdjsonStream<CharacterStatsResult, BoxStream<'static, String>>, ApiError> {
+    let window = resolve_window(window)?;
+    let mut character_names: Vec<String> = Vec::new();
+    for name in names
+        .iter()
+        .map(|name| name.trim())
+        .filter(|name| !name.is_empty())
+    {
+        if !character_names
+            .iter()
+            .any(|known| known.eq_ignore_ascii_case(name))
+        {
+            character_names.push(name.to_string());
+        }
+    }
+    let max_names = config::get().stats.local_max_names;
+    if character_names.len() > max_names {
+        return Err(ApiError::BadRequest(format!(
+            "At most {} names can be looked up at once",
+            max_names
+        )));
+    }
+    let db = conn.into_inner().clone();
+    let redis_pool = redis_pool.inner().clone();
+    let lines = stream! {
+        let db = &db;
+        let mut redis_conn = get_cache(&redis_pool).await;
+        let cached = match redis_conn.as_mut() {
+            Some(redis_conn) => {
+                jager_redis::check_cache_many_character_stats(
+                    redis_conn,
+                    &character_names,
+                    &window.name,
+                )
+                .await
+            }
+            None => vec![None; character_names.len()],
+        };
+        let mut misses: Vec<String> = Vec::new();
+        for (name, stats) in character_names.into_iter().zip(cached) {
+            match stats {
                 Some(stats) => {
-                    jager_redis::cache_character_stats(&mut redis_conn, &character_name, &stats)
+                    yield ndjson_line(&CharacterStatsResult::found(name, stats));
+                }
+                None => misses.push(name),
+            }
+        }
+        let lookups = match name_processing::lookup_character_names(db, &misses).await {
+            Ok(lookups) => lookups,
+            Err(e) => {
+                error!("Failed to look up {} names: {:?}", misses.len(), e);
+                for name in misses {
+                    yield ndjson_line(&CharacterStatsResult::error(
+                        name,
+                        "Failed to look up name".to_string(),
+                    ));
+                }
+                return;
+            }
+        };
+        let mut known: Vec<(String, u64)> = Vec::new();
+        for name in misses {
+            match lookups.get(&name.to_lowercase()) {
+                Some(NameLookup::Found(character_id)) => known.push((name, *character_id)),
+                Some(NameLookup::Ambiguous(character_ids)) => {
+                    let message = format!(
+                        "The name is currently held by more than one character: {:?}",
+                        character_ids
+                    );
+                    yield ndjson_line(&CharacterStatsResult::error(name, message));
+                }
+                _ => {
+                    yield ndjson_line(&CharacterStatsResult::not_found(name));
+                }
+            }
+        }
+        let known_names: Vec<String> = known.iter().map(|(name, _)| name.clone()).collect();
+        match stats_processing::get_many_character_stats(db, known, window).await {
+            Ok(results) => {
+                for result in results {
+                    let cache = redis_conn.as_mut().zip(result.stats.as_ref());
+                    if let Some((redis_conn, stats)) = cache {
+                        jager_redis::cache_character_stats(
+                            redis_conn,
+                            &result.name,
+                            &window.name,
+                            stats,
+                        )
                         .await;
-                    Some(Json(stats))
+                    }
+                    yield ndjson_line(&result);
                 }
-                None => None,
-            },
+            }
             Err(e) => {
-                error!(
-                    "Failed to fetch character stats from db for {}: {:?}",
-                    character_name, e
-                );
-                None
+                error!("Failed to fetch stats for {} names: {:?}", known_names.len(), e);
+                for name in known_names {
+                    yield ndjson_line(&CharacterStatsResult::error(
+                        name,
+                        "Failed to fetch stats".to_string(),
+                    ));
+                }
+            }
+        }
+    };
+    Ok(NdjsonStream(TextStream::from(lines.boxed()), PhantomData))
+}
+
+async fn get_organization_stats(
+    db: &DatabaseConnection,
+    redis_pool: &Pool<RedisConnectionManager>,
+    kind: OrganizationKind,
+    name: String,
+    window: Option<String>,
+) -> Result<Json<OrganizationStats>, ApiError> {
+    let window = resolve_window(window)?;
+    let mut redis_conn = get_cache(redis_pool).await;
+    if let Some(redis_conn) = redis_conn.as_mut() {
+        if let Some(stats) =
+            jager_redis::check_cache_organization_stats(redis_conn, kind, &name, &window.name).await
+        {
+            return Ok(Json(stats));
+        }
+    }
+    match organization_stats_processing::get_organization_stats(db, kind, &name, window).await? {
+        Some(stats) => {
+            if let Some(redis_conn) = redis_conn.as_mut() {
+                jager_redis::cache_organization_stats(
+                    redis_conn,
+                    kind,
+                    &name,
+                    &window.name,
+                    &stats,
+                )
+                .await;
             }
-        },
+            Ok(Json(stats))
+        }
+        None => Err(ApiError::not_found(kind.name(), &name)),
     }
 }
 
-#[derive(Deserialize)]
-struct RedisConfig {
-    redis_url: String,
+#[openapi(tag = "Organizations")]
+#[get("/corporation_stats/<name>?<window>")]
+async fn get_corporation_stats(
+    conn: DbConn<'_>,
+    redis_pool: &State<Pool<RedisConnectionManager>>,
+    name: String,
+    window: Option<String>,
+) -> Result<Json<OrganizationStats>, ApiError> {
+    get_organization_stats(
+        conn.into_inner(),
+        redis_pool,
+        OrganizationKind::Corporation,
+        name,
+        window,
+    )
+    .await
 }
 
-#[launch]
-async fn rocket() -> _ {
-    use figment::{
-        providers::{Format, Toml},
+#[openapi(tag = "Organizations")]
+#[get("/alliance_stats/<name>?<window>")]
+async fn get_alliance_stats(
+    conn: DbConn<'_>,
+    redis_pool: &State<Pool<RedisConnectionManager>>,
+    name: String,
+    window: Option<String>,
+) -> Result<Json<OrganizationStats>, ApiError> {
+    get_organization_stats(
+        conn.into_inner(),
+        redis_pool,
+        OrganizationKind::Alliance,
+        name,
+        window,
+    )
+    .await
+}
+
+#[openapi(tag = "Characters")]
+#[get("/character/<character_name>/activity?<window>")]
+async fn get_character_activity(
+    conn: DbConn<'_>,
+    character_name: String,
+    window: Option<String>,
+) -> Result<Json<ActivityProfile>, ApiError> {
+    let window = resolve_window(window)?;
+    let db = conn.into_inner();
+    stats_processing::get_character_activity(db, character_name.clone(), window)
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("character", &character_name))
+}
+
+#[openapi(tag = "Characters")]
+#[get("/character/<character_name>/associates?<window>")]
+async fn get_character_associates(
+    conn: DbConn<'_>,
+    character_name: String,
+    window: Option<String>,
+) -> Result<Json<Associates>, ApiError> {
+    let window = resolve_window(window)?;
+    let db = conn.into_inner();
+    associates_processing::get_character_associates(db, character_name.clone(), window)
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("character", &character_name))
+}
+
+#[openapi(tag = "Characters")]
+#[get("/character/<character_name>/locations?<window>")]
+async fn get_character_locations(
+    conn: DbConn<'_>,
+    character_name: String,
+    window: Option<String>,
+) -> Result<Json<LocationProfile>, ApiError> {
+    let window = resolve_window(window)?;
+    let db = conn.into_inner();
+    location_processing::get_character_locations(db, character_name.clone(), window)
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("character", &character_name))
+}
+
+#[openapi(tag = "Characters")]
+#[get("/character/<character_name>/killmails?<side>&<from>&<to>&<ship_type_id>&<solo>&<cursor>&<limit>")]
+#[allow(clippy::too_many_arguments)]
+async fn get_character_killmails(
+    conn: DbConn<'_>,
+    character_name: String,
+    side: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    ship_type_id: Option<u64>,
+    solo: Option<bool>,
+    cursor: Option<String>,
+    limit: Option<u64>,
+) -> Result<Json<CharacterKillmailPage>, ApiError> {
+    let filter = CharacterKillmailFilter::parse(
+        side.as_deref(),
+        from.as_deref(),
+        to.as_deref(),
+        ship_type_id,
+        solo,
+        cursor.as_deref(),
+        limit,
+    )
+    .map_err(ApiError::BadRequest)?;
+    let db = conn.into_inner();
+    killmail_detail_processing::get_character_killmails(db, character_name.clone(), &filter)
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("character", &character_name))
+}
+
+#[openapi(tag = "Killmails")]
+#[get("/killmails/<killmail_id>")]
+async fn get_killmail(
+    conn: DbConn<'_>,
+    killmail_id: u64,
+) -> Result<Json<KillmailDetail>, ApiError> {
+    killmail_detail_processing::get_killmail_detail(conn.into_inner(), killmail_id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("No killmail with id {}", killmail_id)))
+}
+
+#[openapi(tag = "Killmails")]
+#[post("/killmails/search", data = "<search>")]
+async fn search_killmails(
+    conn: DbConn<'_>,
+    search: Json<KillmailSearch>,
+) -> Result<Json<KillmailSearchResults>, ApiError> {
+    let limit =
+        killmail_search_processing::validate_search(&search).map_err(ApiError::BadRequest)?;
+    Ok(Json(
+        killmail_search_processing::search_killmails(conn.into_inner(), &search, limit).await?,
+    ))
+}
+
+#[openapi(tag = "Search")]
+#[get("/search?<q>&<kind>&<limit>")]
+async fn search(
+    conn: DbConn<'_>,
+    q: String,
+    kind: Option<String>,
+    limit: Option<u64>,
+) -> Result<Json<SearchResults>, ApiError> {
+    let query = SearchQuery::parse(&q, kind.as_deref(), limit).map_err(ApiError::BadRequest)?;
+    Ok(Json(
+        search_processing::search(conn.into_inner(), &query).await?,
+    ))
+}
+
+#[openapi(tag = "Analysis")]
+#[get("/compare?<names>&<window>")]
+async fn get_comparison(
+    conn: DbConn<'_>,
+    names: String,
+    window: Option<String>,
+) -> Result<Json<Comparison>, ApiError> {
+    let window = resolve_window(window)?;
+    let mut pilot_names: Vec<String> = Vec::new();
+    for name in names
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+    {
+        if !pilot_names
+            .iter()
+            .any(|known| known.eq_ignore_ascii_case(name))
+        {
+            pilot_names.push(name.to_string());
+        }
+    }
+    let max_names = config::get().stats.compare_max_names;
+    if pilot_names.len() < 2 || pilot_names.len() > max_names {
+        return Err(ApiError::BadRequest(format!(
+            "Expected between 2 and {} comma separated names, got {}",
+            max_names,
+            pilot_names.len()
+        )));
+    }
+    let db = conn.into_inner();
+    Ok(Json(
+        compare_processing::compare_characters(db, pilot_names, window).await?,
+    ))
+}
+
+#[openapi(tag = "Analysis")]
+#[post("/local_analysis?<window>", data = "<local>")]
+async fn get_local_analysis(
+    conn: DbConn<'_>,
+    local: Json<LocalList>,
+    window: Option<String>,
+) -> Result<Json<LocalAnalysis>, ApiError> {
+    let window = match window {
+        Some(_) => resolve_window(window)?,
+        None => config::get().stats.get_local_default_window(),
     };
+    let mut names: Vec<String> = Vec::new();
+    for name in local
+        .into_inner()
+        .names
+        .iter()
+        .map(|name| name.trim())
+        .filter(|name| !name.is_empty())
+    {
+        if !names.iter().any(|known| known.eq_ignore_ascii_case(name)) {
+            names.push(name.to_string());
+        }
+    }
+    let max_names = config::get().stats.local_max_names;
+    if names.len() > max_names {
+        return Err(ApiError::BadRequest(format!(
+            "At most {} names can be analyzed at once",
+            max_names
+        )));
+    }
+    let db = conn.into_inner();
+    Ok(Json(
+        fleet_processing::analyze_local(db, names, window).await?,
+    ))
+}
+
+fn resolve_range(
+    from: Option<String>,
+    to: Option<String>,
+    interval: Option<String>,
+) -> Result<TimeseriesRange, ApiError> {
+    TimeseriesRange::parse(from.as_deref(), to.as_deref(), interval.as_deref())
+        .map_err(ApiError::BadRequest)
+}
+
+async fn get_timeseries(
+    db: &DatabaseConnection,
+    redis_pool: &Pool<RedisConnectionManager>,
+    subject: TimeseriesSubject,
+    range: TimeseriesRange,
+) -> Result<Json<Timeseries>, ApiError> {
+    let mut redis_conn = get_cache(redis_pool).await;
+    if let Some(redis_conn) = redis_conn.as_mut() {
+        if let Some(timeseries) =
+            jager_redis::check_cache_timeseries(redis_conn, subject, &range).await
+        {
+            return Ok(Json(timeseries));
+        }
+    }
+    let timeseries = timeseries_processing::get_timeseries(db, subject, &range).await?;
+    if let Some(redis_conn) = redis_conn.as_mut() {
+        jager_redis::cache_timeseries(redis_conn, subject, &range, &timeseries).await;
+    }
+    Ok(Json(timeseries))
+}
+
+#[openapi(tag = "Characters")]
+#[get("/character/<character_name>/timeseries?<from>&<to>&<interval>")]
+async fn get_character_timeseries(
+    conn: DbConn<'_>,
+    redis_pool: &State<Pool<RedisConnectionManager>>,
+    character_name: String,
+    from: Option<String>,
+    to: Option<String>,
+    interval: Option<String>,
+) -> Result<Json<Timeseries>, ApiError> {
+    let range = resolve_range(from, to, interval)?;
+    let db = conn.into_inner();
+    let character_id =
+        match stats_processing::get_or_update_character_public_info(db, character_name.clone())
+            .await?
+        {
+            Some(char_info) => char_info.character_id,
+            None => return Err(ApiError::not_found("character", &character_name)),
+        };
+    get_timeseries(
+        db,
+        redis_pool,
+        TimeseriesSubject::Character(character_id),
+        range,
+    )
+    .await
+}
+
+async fn get_organization_timeseries(
+    db: &DatabaseConnection,
+    redis_pool: &Pool<RedisConnectionManager>,
+    kind: OrganizationKind,
+    name: String,
+    range: TimeseriesRange,
+) -> Result<Json<Timeseries>, ApiError> {
+    match organization_stats_processing::get_organization_id(db, kind, &name).await? {
+        Some(organization_id) => {
+            get_timeseries(
+                db,
+                redis_pool,
+                TimeseriesSubject::Organization(kind, organization_id),
+                range,
+            )
+            .await
+        }
+        None => Err(ApiError::not_found(kind.name(), &name)),
+    }
+}
 
-    let figment = rocket::Config::figment()
-        .merge(rocket::Config::default())
-        .merge(Toml::file("Rocket.toml").nested());
-    let redis_config: RedisConfig = figment.extract().unwrap();
-    let pool = jager_redis::get_redis_pool(redis_config.redis_url).await;
+#[openapi(tag = "Organizations")]
+#[get("/corporation/<name>/timeseries?<from>&<to>&<interval>")]
+async fn get_corporation_timeseries(
+    conn: DbConn<'_>,
+    redis_pool: &State<Pool<RedisConnectionManager>>,
+    name: String,
+    from: Option<String>,
+    to: Option<String>,
+    interval: Option<String>,
+) -> Result<Json<Timeseries>, ApiError> {
+    let range = resolve_range(from, to, interval)?;
+    get_organization_timeseries(
+        conn.into_inner(),
+        redis_pool,
+        OrganizationKind::Corporation,
+        name,
+        range,
+    )
+    .await
+}
+
+#[openapi(tag = "Organizations")]
+#[get("/alliance/<name>/timeseries?<from>&<to>&<interval>")]
+async fn get_alliance_timeseries(
+    conn: DbConn<'_>,
+    redis_pool: &State<Pool<RedisConnectionManager>>,
+    name: String,
+    from: Option<String>,
+    to: Option<String>,
+    interval: Option<String>,
+) -> Result<Json<Timeseries>, ApiError> {
+    let range = resolve_range(from, to, interval)?;
+    get_organization_timeseries(
+        conn.into_inner(),
+        redis_pool,
+        OrganizationKind::Alliance,
+        name,
+        range,
+    )
+    .await
+}
+
+async fn get_organization_activity(
+    db: &DatabaseConnection,
+    kind: OrganizationKind,
+    name: String,
+    window: Option<String>,
+) -> Result<Json<ActivityProfile>, ApiError> {
+    let window = resolve_window(window)?;
+    let organization_id =
+        match organization_stats_processing::get_organization_id(db, kind, &name).await? {
+            Some(organization_id) => organization_id,
+            None => return Err(ApiError::not_found(kind.name(), &name)),
+        };
+    Ok(Json(
+        activity_processing::get_organization_activity(db, kind, organization_id, window).await?,
+    ))
+}
+
+#[openapi(tag = "Organizations")]
+#[get("/corporation/<name>/activity?<window>")]
+async fn get_corporation_activity(
+    conn: DbConn<'_>,
+    name: String,
+    window: Option<String>,
+) -> Result<Json<ActivityProfile>, ApiError> {
+    get_organization_activity(
+        conn.into_inner(),
+        OrganizationKind::Corporation,
+        name,
+        window,
+    )
+    .await
+}
+
+#[openapi(tag = "Organizations")]
+#[get("/alliance/<name>/activity?<window>")]
+async fn get_alliance_activity(
+    conn: DbConn<'_>,
+    name: String,
+    window: Option<String>,
+) -> Result<Json<ActivityProfile>, ApiError> {
+    get_organization_activity(conn.into_inner(), OrganizationKind::Alliance, name, window).await
+}
+
+#[launch]
+async fn rocket() -> _ {
+    let config = config::init().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1)
+    });
+    let pool = jager_redis::get_redis_pool(&config.redis).await;
     backend::logging::setup_logging();
-    rocket::custom(figment)
+    // Rocket's own settings (address, port, workers) still come from Rocket.toml
+    rocket::build()
         .attach(Db::init())
-        .attach(AdHoc::config::<RedisConfig>())
         .register("/", catchers![not_found])
         .manage(pool)
-        .mount("/", routes![index])
-        .mount("/", routes![get_character_stats])
+        .mount(
+            "/v1",
+            openapi_get_routes![
+                index,
+                get_character_stats,
+                get_many_character_stats,
+                get_corporation_stats,
+                get_alliance_stats,
+                get_character_activity,
+                get_corporation_activity,
+                get_alliance_activity,
+                get_character_associates,
+                get_character_locations,
+                get_character_killmails,
+                get_killmail,
+                search_killmails,
+                search,
+                get_comparison,
+                get_local_analysis,
+                get_character_timeseries,
+                get_corporation_timeseries,
+                get_alliance_timeseries
+            ],
+        )
 }
@@ -3,6 +3,7 @@ extern crate log;
 
 extern crate pbr;
 
+use backend::config;
 use backend::database::establish_connection;
 use backend::killmail_processing;
 use backend::zkill;
@@ -12,11 +13,15 @@ use pbr::ProgressBar;
 
 #[tokio::main]
 async fn main() {
+    let config = config::init().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1)
+    });
     backend::logging::setup_logging();
     info!("Establishing connection");
     let db = establish_connection().await.unwrap();
     let now: NaiveDate = Utc::today().naive_utc();
-    let dates = zkill::get_dates(now, 90);
+    let dates = zkill::get_dates(now, config.zkill.history_days);
     let requests = zkill::get_history_records(dates).await;
     let mut pb = ProgressBar::new(requests.len().try_into().unwrap());
     println!("Fetching {} killmails", requests.len());
@@ -1,9 +1,16 @@
+use backend::config;
 use backend::database;
-use backend::entity::{esi_categories, esi_groups, esi_types, factions};
+use backend::entity::{
+    constellations, esi_categories, esi_groups, esi_types, factions, regions, solar_systems,
+};
 use backend::esi;
 
 #[tokio::main]
 async fn main() {
+    config::init().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1)
+    });
     backend::logging::setup_logging();
     let db = database::establish_connection().await.unwrap();
     let esi_factions_list = esi::get_factions().await.unwrap();
@@ -33,4 +40,30 @@ async fn main() {
         .map(esi_types::ActiveModel::from)
         .collect();
     database::insert_if_not_present_types(&db, type_insertables).await;
+    let esi_regions = esi::get_esi_regions(esi::get_region_list().await.unwrap()).await;
+    let constellation_ids: Vec<u64> = esi_regions
+        .iter()
+        .flat_map(|region| region.constellations.clone())
+        .collect();
+    let region_insertables: Vec<regions::ActiveModel> = esi_regions
+        .into_iter()
+        .map(regions::ActiveModel::from)
+        .collect();
+    database::insert_if_not_present(&db, region_insertables, "region").await;
+    let esi_constellations = esi::get_esi_constellations(constellation_ids).await;
+    let system_ids: Vec<u64> = esi_constellations
+        .iter()
+        .flat_map(|constellation| constellation.systems.clone())
+        .collect();
+    let constellation_insertables: Vec<constellations::ActiveModel> = esi_constellations
+        .into_iter()
+        .map(constellations::ActiveModel::from)
+        .collect();
+    database::insert_if_not_present(&db, constellation_insertables, "constellation").await;
+    let esi_systems = esi::get_esi_solar_systems(system_ids).await;
+    let system_insertables: Vec<solar_systems::ActiveModel> = esi_systems
+        .into_iter()
+        .map(solar_systems::ActiveModel::from)
+        .collect();
+    database::insert_if_not_present(&db, system_insertables, "solar system").await;
 }
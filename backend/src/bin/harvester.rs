@@ -1,6 +1,7 @@
 #[macro_use]
 extern crate log;
 
+use backend::config;
 use backend::database;
 use backend::killmail_processing;
 use backend::logging;
@@ -12,11 +13,14 @@ use std::time;
 
 use websocket_lite::{ClientBuilder, Message, Opcode};
 
-const WEBSOCKET_URL: &str = "wss://zkillboard.com/websocket/";
 const SUBSCRIPTION_MESSAGE: &str = "{\"action\":\"sub\", \"channel\":\"killstream\"}";
 
 #[tokio::main]
 async fn main() {
+    let config = config::init().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1)
+    });
     info!("Establishing database connection");
     let db = database::establish_connection().await.unwrap();
     let (tx, rx): (Sender<Message>, Receiver<Message>) = mpsc::channel();
@@ -33,8 +37,9 @@ async fn main() {
             }
         }
     });
-    let client_builder = ClientBuilder::new(WEBSOCKET_URL).unwrap();
-    info!("Connected to websocket url: {}", WEBSOCKET_URL);
+    let websocket_url = &config.zkill.websocket_url;
+    let client_builder = ClientBuilder::new(websocket_url).unwrap();
+    info!("Connected to websocket url: {}", websocket_url);
     let mut client = client_builder.connect().unwrap();
     let sub_msg = Message::text(SUBSCRIPTION_MESSAGE);
     let _result = client.send(sub_msg).unwrap();
@@ -0,0 +1,43 @@
+#[macro_use]
+extern crate log;
+
+use backend::config;
+use backend::database::establish_connection;
+use backend::valuation_processing;
+use std::env;
+
+const USAGE: &str = "Usage: price_harvester [--revalue]";
+
+#[tokio::main]
+async fn main() {
+    config::init().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1)
+    });
+    backend::logging::setup_logging();
+    let mut revalue = false;
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--revalue" => revalue = true,
+            _ => {
+                eprintln!("{}", USAGE);
+                return;
+            }
+        }
+    }
+    info!("Establishing connection");
+    let db = establish_connection().await.unwrap();
+    match valuation_processing::harvest_prices(&db).await {
+        Ok(count) => info!("Fetched {} prices", count),
+        Err(e) => {
+            error!("Failed to fetch prices: {:?}", e);
+            return;
+        }
+    }
+    if revalue {
+        match valuation_processing::revalue_all(&db).await {
+            Ok(count) => info!("Revalued {} killmails", count),
+            Err(e) => error!("Failed to revalue killmails: {:?}", e),
+        }
+    }
+}
@@ -0,0 +1,52 @@
+#[macro_use]
+extern crate log;
+
+use backend::config;
+use backend::database::establish_connection;
+use backend::retention;
+use std::env;
+use std::path::PathBuf;
+
+const USAGE: &str = "Usage: pruner [--killmail-days <days>] [--archive <path.jsonl.gz>]";
+
+#[tokio::main]
+async fn main() {
+    let config = config::init().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1)
+    });
+    backend::logging::setup_logging();
+    let mut policy = config.retention.clone();
+    let mut archive_path: Option<PathBuf> = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--killmail-days" => {
+                let days = args.next().and_then(|days| days.parse::<i64>().ok());
+                if days.is_none() {
+                    eprintln!("{}", USAGE);
+                    return;
+                }
+                policy.killmail_days = days;
+            }
+            "--archive" => match args.next() {
+                Some(path) => archive_path = Some(PathBuf::from(path)),
+                None => {
+                    eprintln!("{}", USAGE);
+                    return;
+                }
+            },
+            _ => {
+                eprintln!("{}", USAGE);
+                return;
+            }
+        }
+    }
+    info!("Establishing connection");
+    let db = establish_connection().await.unwrap();
+    info!("Applying retention policy {:?}", policy);
+    match retention::apply_policy(&db, &policy, archive_path.as_deref()).await {
+        Ok(report) => info!("Finished pruning: {:?}", report),
+        Err(e) => error!("Pruning failed: {:?}", e),
+    }
+}
@@ -0,0 +1,98 @@
+#[macro_use]
+extern crate log;
+
+use backend::config;
+use backend::database::establish_connection;
+use backend::entity::prelude::*;
+use backend::name_processing;
+use backend::name_processing::NameLookup;
+use backend::stats_processing;
+use sea_orm::EntityTrait;
+use std::env;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+static QUERY_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Log as usual, except that the statements sqlx logs are counted instead of
+/// printed
+fn setup_logging() {
+    fern::Dispatch::new()
+        .chain(
+            fern::Dispatch::new()
+                .level(log::LevelFilter::Off)
+                .level_for("sqlx::query", log::LevelFilter::Trace)
+                .chain(fern::Output::call(|_| {
+                    QUERY_COUNT.fetch_add(1, Ordering::Relaxed);
+                })),
+        )
+        .chain(
+            fern::Dispatch::new()
+                .level(log::LevelFilter::Info)
+                .level_for("sqlx::query", log::LevelFilter::Off)
+                .chain(std::io::stdout()),
+        )
+        .apply()
+        .unwrap();
+}
+
+/// Compute the stats of each character named on the command line in the
+/// default window and report how long it took and how many queries it needed
+#[tokio::main]
+async fn main() {
+    // The queries are counted from sqlx's statement log
+    env::set_var("JAGER_DATABASE__SQLX_LOGGING", "true");
+    config::init().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1)
+    });
+    setup_logging();
+    info!("Establishing connection");
+    let db = establish_connection().await.unwrap();
+    let names: Vec<String> = env::args().skip(1).collect();
+    if names.is_empty() {
+        eprintln!("Usage: stats_bench <character name>...");
+        return;
+    }
+    let window = config::get().stats.get_default_window();
+    for name in names {
+        let character_id = match name_processing::lookup_character_name(&db, &name).await {
+            Ok(NameLookup::Found(character_id)) => character_id,
+            Ok(lookup) => {
+                println!("{}: {:?}", name, lookup);
+                continue;
+            }
+            Err(e) => {
+                error!("Couldn't look up {}: {:?}", name, e);
+                continue;
+            }
+        };
+        let character = match CharacterPublicInfo::find_by_id(character_id).one(&db).await {
+            Ok(Some(character)) => character,
+            Ok(None) => {
+                println!("{}: no public info", name);
+                continue;
+            }
+            Err(e) => {
+                error!("Couldn't load {}: {:?}", name, e);
+                continue;
+            }
+        };
+        QUERY_COUNT.store(0, Ordering::Relaxed);
+        let start_time = Instant::now();
+        let stats = stats_processing::get_stats_for_characters(&db, &[character], window)
+            .await
+            .unwrap()
+            .remove(&character_id)
+            .unwrap();
+        let duration = start_time.elapsed().as_millis();
+        println!(
+            "{}: {} kills, {} losses, {} queries, {}ms",
+            name,
+            stats.kill_loss_ratio.kills,
+            stats.kill_loss_ratio.losses,
+            QUERY_COUNT.load(Ordering::Relaxed),
+            duration
+        );
+    }
+}
@@ -0,0 +1,21 @@
+#[macro_use]
+extern crate log;
+
+use backend::config;
+use backend::database::establish_connection;
+use backend::summary_processing;
+
+#[tokio::main]
+async fn main() {
+    config::init().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1)
+    });
+    backend::logging::setup_logging();
+    info!("Establishing connection");
+    let db = establish_connection().await.unwrap();
+    match summary_processing::rebuild(&db).await {
+        Ok(_) => info!("Rebuilt character daily stats"),
+        Err(e) => error!("Failed to rebuild character daily stats: {:?}", e),
+    }
+}
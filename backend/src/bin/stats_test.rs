@@ -3,14 +3,20 @@ extern crate log;
 
 extern crate pbr;
 
+use backend::config;
 use backend::database::establish_connection;
 use backend::stats_processing;
 
 #[tokio::main]
 async fn main() {
+    config::init().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1)
+    });
     backend::logging::setup_logging();
     info!("Establishing connection");
     let db = establish_connection().await.unwrap();
-    let res = stats_processing::get_character_stats(&db, "Darkside 34".to_string()).await;
+    let window = config::get().stats.get_default_window();
+    let res = stats_processing::get_character_stats(&db, "Darkside 34".to_string(), window).await;
     println!("{:?}", res);
 }
@@ -0,0 +1,214 @@
+use crate::associates_processing;
+use crate::config;
+use crate::config::StatsWindow;
+use crate::entity::*;
+use crate::killmail_processing::ProcessingError;
+use crate::stats_processing;
+use crate::stats_processing::CharacterStats;
+use chrono::NaiveDateTime;
+use futures::{stream, StreamExt};
+use schemars::JsonSchema;
+use sea_orm::{DatabaseConnection, DbBackend, DbErr, FromQueryResult, Statement, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const SHARED_KILLMAIL_LIMIT: u64 = 100;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct ComparedPilot {
+    pub name: String,
+    pub character_id: Option<u64>,
+    /// `None` if we don't know the pilot
+    pub stats: Option<CharacterStats>,
+}
+
+/// Someone who flies with more than one of the compared pilots
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct SharedAssociate {
+    pub character_id: u64,
+    pub character_name: Option<String>,
+    /// Killmails shared with each compared pilot, in the order they were given
+    pub counts: Vec<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SharedKillmailKind {
+    /// The pilots were all attackers
+    Together,
+    /// One of the pilots killed another
+    Against,
+}
+
+/// A killmail more than one of the compared pilots appears on
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct SharedKillmail {
+    pub killmail_id: u64,
+    pub killmail_time: NaiveDateTime,
+    pub kind: SharedKillmailKind,
+    /// Compared pilots among the attackers
+    pub attackers: Vec<u64>,
+    /// The compared pilot who died, if any
+    pub victim: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct Comparison {
+    pub window: String,
+    pub pilots: Vec<ComparedPilot>,
+    pub shared_associates: Vec<SharedAssociate>,
+    pub shared_killmails: Vec<SharedKillmail>,
+}
+
+async fn get_shared_associates(
+    db: &DatabaseConnection,
+    character_ids: &[Option<u64>],
+    window: &StatsWindow,
+) -> Result<Vec<SharedAssociate>, DbErr> {
+    let mut shared: BTreeMap<u64, SharedAssociate> = BTreeMap::new();
+    for (index, character_id) in character_ids.iter().enumerate() {
+        let character_id = match character_id {
+            Some(character_id) => *character_id,
+            None => continue,
+        };
+        let associates = associates_processing::get_associates(db, character_id, window).await?;
+        for associate in associates.characters {
+            if character_ids.contains(&Some(associate.id)) {
+                continue;
+            }
+            let entry = shared
+                .entry(associate.id)
+                .or_insert_with(|| SharedAssociate {
+                    character_id: associate.id,
+                    character_name: associate.name.clone(),
+                    counts: vec![0; character_ids.len()],
+                });
+            entry.counts[index] = associate.count;
+        }
+    }
+    let mut shared: Vec<SharedAssociate> = shared
+        .into_values()
+        .filter(|associate| associate.counts.iter().filter(|count| **count > 0).count() > 1)
+        .collect();
+    shared.sort_by(|a, b| {
+        b.counts
+            .iter()
+            .sum::<usize>()
+            .cmp(&a.counts.iter().sum::<usize>())
+    });
+    Ok(shared)
+}
+
+#[derive(Debug, FromQueryResult)]
+struct SharedKillmailSide {
+    killmail_id: u64,
+    killmail_time: NaiveDateTime,
+    character_id: u64,
+    is_loss: i32,
+}
+
+/// The newest killmails in `window` with more than one of the pilots on them,
+/// and which side each of them was on
+async fn get_shared_killmails(
+    db: &DatabaseConnection,
+    character_ids: &[u64],
+    window: &StatsWindow,
+) -> Result<Vec<SharedKillmail>, DbErr> {
+    if character_ids.len() < 2 {
+        return Ok(Vec::new());
+    }
+    let mut values: Vec<Value> = Vec::new();
+    let mut shared_sql = format!(
+        r#"SELECT s.killmail_id, k.killmail_time
+        FROM ({}) s
+        JOIN killmails k ON k.killmail_id = s.killmail_id
+        WHERE 1 = 1"#,
+        stats_processing::character_killmails_sql(&mut values, character_ids)
+    );
+    stats_processing::push_window_filter(&mut shared_sql, &mut values, window);
+    shared_sql.push_str(
+        r#" GROUP BY s.killmail_id, k.killmail_time
+        HAVING COUNT(DISTINCT s.character_id) > 1
+        ORDER BY k.killmail_time DESC, s.killmail_id DESC
+        LIMIT ?"#,
+    );
+    values.push(SHARED_KILLMAIL_LIMIT.into());
+    let sql = format!(
+        r#"SELECT shared.killmail_id, shared.killmail_time, c.character_id, c.is_loss
+        FROM ({}) shared
+        JOIN ({}) c ON c.killmail_id = shared.killmail_id
+        ORDER BY shared.killmail_time DESC, shared.killmail_id DESC"#,
+        shared_sql,
+        stats_processing::character_killmails_sql(&mut values, character_ids)
+    );
+    let sides = SharedKillmailSide::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::MySql,
+        &sql,
+        values,
+    ))
+    .all(db)
+    .await?;
+    let mut shared: Vec<SharedKillmail> = Vec::new();
+    for side in sides {
+        if shared.last().map(|killmail| killmail.killmail_id) != Some(side.killmail_id) {
+            shared.push(SharedKillmail {
+                killmail_id: side.killmail_id,
+                killmail_time: side.killmail_time,
+                kind: SharedKillmailKind::Together,
+                attackers: Vec::new(),
+                victim: None,
+            });
+        }
+        if let Some(killmail) = shared.last_mut() {
+            if side.is_loss == 1 {
+                killmail.kind = SharedKillmailKind::Against;
+                killmail.victim = Some(side.character_id);
+            } else {
+                killmail.attackers.push(side.character_id);
+            }
+        }
+    }
+    Ok(shared)
+}
+
+/// Stats for several pilots side by side, with who they have in common and
+/// the killmails they share. Names are resolved concurrently and the stats of
+/// every known pilot are computed together.
+pub async fn compare_characters(
+    db: &DatabaseConnection,
+    names: Vec<String>,
+    window: &StatsWindow,
+) -> Result<Comparison, ProcessingError> {
+    let resolved: Vec<Result<Option<character_public_info::Model>, ProcessingError>> =
+        stream::iter(names.clone())
+            .map(|name| stats_processing::get_or_update_character_public_info(db, name))
+            .buffered(config::get().concurrency.bulk_stats)
+            .collect()
+            .await;
+    let mut characters: Vec<character_public_info::Model> = Vec::new();
+    let mut character_ids: Vec<Option<u64>> = Vec::with_capacity(names.len());
+    for character in resolved {
+        let character = character?;
+        character_ids.push(character.as_ref().map(|character| character.character_id));
+        characters.extend(character);
+    }
+    let mut stats = stats_processing::get_stats_for_characters(db, &characters, window).await?;
+    let pilots: Vec<ComparedPilot> = names
+        .into_iter()
+        .zip(character_ids.iter())
+        .map(|(name, character_id)| ComparedPilot {
+            name,
+            character_id: *character_id,
+            stats: character_id.and_then(|character_id| stats.remove(&character_id)),
+        })
+        .collect();
+    let shared_associates = get_shared_associates(db, &character_ids, window).await?;
+    let known_ids: Vec<u64> = character_ids.into_iter().flatten().collect();
+    let shared_killmails = get_shared_killmails(db, &known_ids, window).await?;
+    Ok(Comparison {
+        window: window.name.clone(),
+        pilots,
+        shared_associates,
+        shared_killmails,
+    })
+}
@@ -0,0 +1,547 @@
+use crate::retention::RetentionPolicy;
+use chrono::{Duration, NaiveDateTime, Utc};
+use dotenv::dotenv;
+use figment::providers::{Env, Format, Serialized, Toml};
+use figment::Figment;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fmt;
+
+const DEFAULT_CONFIG_PATH: &str = "Jager.toml";
+
+static CONFIG: OnceCell<JagerConfig> = OnceCell::new();
+
+/// Jager runs on MySQL 5.7 or newer, the name search migration needs its
+/// ngram full text parser
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DatabaseConfig {
+    pub url: String,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub connect_timeout_secs: u64,
+    pub idle_timeout_secs: u64,
+    pub max_lifetime_secs: u64,
+    pub sqlx_logging: bool,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig {
+            url: String::new(),
+            max_connections: 10,
+            min_connections: 1,
+            connect_timeout_secs: 8,
+            idle_timeout_secs: 60,
+            max_lifetime_secs: 1800,
+            sqlx_logging: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RedisConfig {
+    pub url: String,
+    pub password: Option<String>,
+    pub pool_size: u32,
+    pub expire_interval_secs: usize,
+    /// How long a request waits for a cache connection before going without
+    pub connection_timeout_ms: u64,
+}
+
+impl Default for RedisConfig {
+    fn default() -> Self {
+        RedisConfig {
+            url: "redis://127.0.0.1/".to_string(),
+            password: None,
+            pool_size: 10,
+            expire_interval_secs: 14400,
+            connection_timeout_ms: 500,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EsiConfig {
+    pub url: String,
+    pub version: String,
+    pub datasource: String,
+    pub retry_count: u64,
+    pub concurrency: usize,
+}
+
+impl Default for EsiConfig {
+    fn default() -> Self {
+        EsiConfig {
+            url: "https://esi.evetech.net".to_string(),
+            version: "latest".to_string(),
+            datasource: "tranquility".to_string(),
+            retry_count: 10,
+            concurrency: 100,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ZKillConfig {
+    pub url: String,
+    pub websocket_url: String,
+    pub history_days: u64,
+    pub history_concurrency: usize,
+}
+
+impl Default for ZKillConfig {
+    fn default() -> Self {
+        ZKillConfig {
+            url: "https://zkillboard.com/api".to_string(),
+            websocket_url: "wss://zkillboard.com/websocket/".to_string(),
+            history_days: 90,
+            history_concurrency: 10,
+        }
+    }
+}
+
+/// How many requests or queries each stage of the pipeline runs at once
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConcurrencyConfig {
+    pub killmails: usize,
+    pub organizations: usize,
+    pub database_inserts: usize,
+    /// Stale characters refreshed from ESI at once for a bulk stats request or
+    /// a comparison
+    pub bulk_stats: usize,
+}
+
+impl Default for ConcurrencyConfig {
+    fn default() -> Self {
+        ConcurrencyConfig {
+            killmails: 20,
+            organizations: 10,
+            database_inserts: 10,
+            bulk_stats: 10,
+        }
+    }
+}
+
+/// A period stats can be broken down by. `hours` of `None` means all time.
+/// Windows start exactly `hours` ago, even where they're summed from daily
+/// stats.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsWindow {
+    pub name: String,
+    pub hours: Option<i64>,
+}
+
+impl StatsWindow {
+    fn new(name: &str, hours: Option<i64>) -> StatsWindow {
+        StatsWindow {
+            name: name.to_string(),
+            hours,
+        }
+    }
+
+    /// When the window starts, or `None` for all time
+    pub fn start_time(&self) -> Option<NaiveDateTime> {
+        self.hours
+            .map(|hours| Utc::now().naive_utc() - Duration::hours(hours))
+    }
+
+    pub fn contains(&self, time: NaiveDateTime) -> bool {
+        match self.start_time() {
+            Some(start_time) => time >= start_time,
+            None => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatsConfig {
+    pub windows: Vec<StatsWindow>,
+    pub default_window: String,
+    /// How many entries to return in top-N lists such as ships flown
+    pub top_entries: usize,
+    /// Upper edges of the gang size buckets, inclusive. Gangs larger than the
+    /// last edge go in a final open-ended bucket, and killmails without player
+    /// attackers in a bucket of their own before the first.
+    pub gang_size_buckets: Vec<usize>,
+    /// Longest range a time series can be requested for
+    pub timeseries_max_days: i64,
+    /// Most pilots that can be compared in one request
+    pub compare_max_names: usize,
+    /// Most names a pasted local can hold
+    pub local_max_names: usize,
+    /// Kills two pilots need to have shared to be put in the same fleet
+    pub fleet_min_shared_killmails: usize,
+    /// Window a local is analyzed over when the request doesn't name one
+    pub local_default_window: String,
+    /// Most killmail rows a local analysis reads, newest first
+    pub local_max_sightings: u64,
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        StatsConfig {
+            windows: vec![
+                StatsWindow::new("24h", Some(24)),
+                StatsWindow::new("7d", Some(24 * 7)),
+                StatsWindow::new("30d", Some(24 * 30)),
+                StatsWindow::new("90d", Some(24 * 90)),
+                StatsWindow::new("all", None),
+            ],
+            default_window: "all".to_string(),
+            top_entries: 10,
+            gang_size_buckets: vec![1, 5, 15, 50],
+            timeseries_max_days: 366,
+            compare_max_names: 5,
+            local_max_names: 500,
+            fleet_min_shared_killmails: 2,
+            local_default_window: "7d".to_string(),
+            local_max_sightings: 20_000,
+        }
+    }
+}
+
+impl StatsConfig {
+    pub fn get_window(&self, name: &str) -> Option<&StatsWindow> {
+        self.windows.iter().find(|window| window.name == name)
+    }
+
+    pub fn get_default_window(&self) -> &StatsWindow {
+        self.get_window(&self.default_window)
+            .expect("stats.default_window is validated at startup")
+    }
+
+    pub fn get_local_default_window(&self) -> &StatsWindow {
+        self.get_window(&self.local_default_window)
+            .expect("stats.local_default_window is validated at startup")
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JagerConfig {
+    pub database: DatabaseConfig,
+    pub redis: RedisConfig,
+    pub esi: EsiConfig,
+    pub zkill: ZKillConfig,
+    pub concurrency: ConcurrencyConfig,
+    pub retention: RetentionPolicy,
+    pub stats: StatsConfig,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Parse(Box<figment::Error>),
+    Invalid(Vec<String>),
+}
+
+impl From<figment::Error> for ConfigError {
+    fn from(err: figment::Error) -> ConfigError {
+        ConfigError::Parse(Box::new(err))
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Parse(err) => write!(f, "Couldn't read configuration: {}", err),
+            ConfigError::Invalid(problems) => {
+                write!(f, "Invalid configuration:")?;
+                for problem in problems {
+                    write!(f, "\n  - {}", problem)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl JagerConfig {
+    /// Build the configuration from defaults, then the config file (`Jager.toml`
+    /// or the path in `JAGER_CONFIG`), then the environment. Environment
+    /// variables are prefixed with `JAGER_` and use `__` between sections, e.g.
+    /// `JAGER_DATABASE__MAX_CONNECTIONS`. The older `DATABASE_URL`, `REDIS_URL`
+    /// and `REDIS_AUTH_PW` variables are still honoured.
+    pub fn figment() -> Figment {
+        dotenv().ok();
+        let path = env::var("JAGER_CONFIG").unwrap_or_else(|_| DEFAULT_CONFIG_PATH.to_string());
+        Figment::from(Serialized::defaults(JagerConfig::default()))
+            .merge(Toml::file(path))
+            .merge(
+                Env::raw()
+                    .only(&["DATABASE_URL"])
+                    .map(|_| "database.url".into()),
+            )
+            .merge(Env::raw().only(&["REDIS_URL"]).map(|_| "redis.url".into()))
+            .merge(
+                Env::raw()
+                    .only(&["REDIS_AUTH_PW"])
+                    .map(|_| "redis.password".into()),
+            )
+            .merge(Env::prefixed("JAGER_").split("__"))
+    }
+
+    pub fn load() -> Result<JagerConfig, ConfigError> {
+        let config: JagerConfig = JagerConfig::figment().extract()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems: Vec<String> = Vec::new();
+        if self.database.url.is_empty() {
+            problems.push(
+                "database.url is not set (set DATABASE_URL or database.url in Jager.toml)"
+                    .to_string(),
+            );
+        } else if !self.database.url.starts_with("mysql://") {
+            problems.push("database.url must be a mysql:// url".to_string());
+        }
+        if self.database.max_connections == 0 {
+            problems.push("database.max_connections must be at least 1".to_string());
+        }
+        if self.database.min_connections > self.database.max_connections {
+            problems.push(format!(
+                "database.min_connections ({}) is larger than database.max_connections ({})",
+                self.database.min_connections, self.database.max_connections
+            ));
+        }
+        if !self.redis.url.starts_with("redis://") && !self.redis.url.starts_with("rediss://") {
+            problems.push("redis.url must be a redis:// or rediss:// url".to_string());
+        }
+        if self.redis.pool_size == 0 {
+            problems.push("redis.pool_size must be at least 1".to_string());
+        }
+        if self.redis.connection_timeout_ms == 0 {
+            problems.push("redis.connection_timeout_ms must be at least 1".to_string());
+        }
+        if self.esi.url.is_empty() {
+            problems.push("esi.url is not set".to_string());
+        }
+        if self.zkill.url.is_empty() {
+            problems.push("zkill.url is not set".to_string());
+        }
+        for (name, value) in [
+            ("esi.concurrency", self.esi.concurrency),
+            ("zkill.history_concurrency", self.zkill.history_concurrency),
+            ("stats.top_entries", self.stats.top_entries),
+            ("concurrency.killmails", self.concurrency.killmails),
+            ("concurrency.organizations", self.concurrency.organizations),
+            (
+                "concurrency.database_inserts",
+                self.concurrency.database_inserts,
+            ),
+            ("concurrency.bulk_stats", self.concurrency.bulk_stats),
+        ] {
+            if value == 0 {
+                problems.push(format!("{} must be at least 1", name));
+            }
+        }
+        for (name, days) in [
+            ("retention.killmail_days", self.retention.killmail_days),
+            ("retention.character_days", self.retention.character_days),
+            (
+                "retention.market_price_days",
+                self.retention.market_price_days,
+            ),
+        ] {
+            if let Some(days) = days {
+                if days < 1 {
+                    problems.push(format!("{} must be at least 1, got {}", name, days));
+                }
+            }
+        }
+        for (index, window) in self.stats.windows.iter().enumerate() {
+            if window.name.is_empty() {
+                problems.push(format!("stats.windows[{}] has no name", index));
+            }
+            if self.stats.windows[..index]
+                .iter()
+                .any(|other| other.name == window.name)
+            {
+                problems.push(format!("stats window {} is defined twice", window.name));
+            }
+            if let Some(hours) = window.hours {
+                if hours < 1 {
+                    problems.push(format!(
+                        "stats window {} must cover at least 1 hour, got {}",
+                        window.name, hours
+                    ));
+                }
+            }
+        }
+        if self.stats.timeseries_max_days < 1 {
+            problems.push("stats.timeseries_max_days must be at least 1".to_string());
+        }
+        if self.stats.compare_max_names < 2 {
+            problems.push("stats.compare_max_names must be at least 2".to_string());
+        }
+        if self.stats.local_max_names < 1 {
+            problems.push("stats.local_max_names must be at least 1".to_string());
+        }
+        if self.stats.fleet_min_shared_killmails < 1 {
+            problems.push("stats.fleet_min_shared_killmails must be at least 1".to_string());
+        }
+        if self.stats.local_max_sightings < 1 {
+            problems.push("stats.local_max_sightings must be at least 1".to_string());
+        }
+        if self.stats.gang_size_buckets.is_empty() {
+            problems.push("stats.gang_size_buckets must have at least one edge".to_string());
+        } else if self.stats.gang_size_buckets[0] < 1 {
+            problems.push("stats.gang_size_buckets must start at 1 or more".to_string());
+        }
+        if self
+            .stats
+            .gang_size_buckets
+            .windows(2)
+            .any(|edges| edges[0] >= edges[1])
+        {
+            problems.push("stats.gang_size_buckets must be strictly increasing".to_string());
+        }
+        if self.stats.get_window(&self.stats.default_window).is_none() {
+            problems.push(format!(
+                "stats.default_window {} is not one of stats.windows",
+                self.stats.default_window
+            ));
+        }
+        if self
+            .stats
+            .get_window(&self.stats.local_default_window)
+            .is_none()
+        {
+            problems.push(format!(
+                "stats.local_default_window {} is not one of stats.windows",
+                self.stats.local_default_window
+            ));
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+}
+
+/// Load and validate the configuration once. Binaries call this at startup
+/// and exit with the error so problems show up immediately rather than on
+/// first use.
+pub fn init() -> Result<&'static JagerConfig, ConfigError> {
+    CONFIG.get_or_try_init(JagerConfig::load)
+}
+
+/// The process-wide configuration, loaded on first use if `init` wasn't
+/// called. Panics if it can't be loaded.
+pub fn get() -> &'static JagerConfig {
+    init().unwrap_or_else(|e| panic!("{}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn valid_config() -> JagerConfig {
+        let mut config = JagerConfig::default();
+        config.database.url = "mysql://jager@localhost/jager".to_string();
+        config
+    }
+
+    fn problems(config: &JagerConfig) -> Vec<String> {
+        match config.validate() {
+            Ok(()) => Vec::new(),
+            Err(ConfigError::Invalid(problems)) => problems,
+            Err(e) => panic!("unexpected error {}", e),
+        }
+    }
+
+    #[test]
+    fn defaults_are_valid_once_the_database_is_set() {
+        assert_eq!(problems(&valid_config()), Vec::<String>::new());
+        assert_eq!(problems(&JagerConfig::default()).len(), 1);
+    }
+
+    #[test]
+    fn rejects_bad_urls() {
+        let mut config = valid_config();
+        config.database.url = "postgres://localhost/jager".to_string();
+        config.redis.url = "localhost:6379".to_string();
+        assert_eq!(problems(&config).len(), 2);
+        config.redis.url = "rediss://localhost/".to_string();
+        assert_eq!(problems(&config).len(), 1);
+    }
+
+    #[test]
+    fn rejects_zero_limits() {
+        let mut config = valid_config();
+        config.database.min_connections = 0;
+        config.database.max_connections = 0;
+        config.concurrency.killmails = 0;
+        config.concurrency.bulk_stats = 0;
+        config.stats.local_max_sightings = 0;
+        config.redis.connection_timeout_ms = 0;
+        let problems = problems(&config);
+        assert_eq!(problems.len(), 5);
+        assert!(problems.contains(&"concurrency.killmails must be at least 1".to_string()));
+    }
+
+    #[test]
+    fn rejects_min_connections_above_max() {
+        let mut config = valid_config();
+        config.database.min_connections = 11;
+        assert_eq!(problems(&config).len(), 1);
+        config.database.min_connections = 10;
+        assert!(problems(&config).is_empty());
+    }
+
+    #[test]
+    fn retention_must_keep_at_least_a_day() {
+        let mut config = valid_config();
+        config.retention.killmail_days = Some(1);
+        assert!(problems(&config).is_empty());
+        config.retention.killmail_days = Some(0);
+        config.retention.character_days = Some(-5);
+        config.retention.market_price_days = Some(-5);
+        assert_eq!(problems(&config).len(), 3);
+    }
+
+    #[test]
+    fn checks_stats_windows() {
+        let mut config = valid_config();
+        config.stats.windows.push(StatsWindow::new("7d", Some(0)));
+        config.stats.windows.push(StatsWindow::new("", None));
+        assert_eq!(problems(&config).len(), 3);
+
+        let mut config = valid_config();
+        config.stats.default_window = "1y".to_string();
+        config.stats.local_default_window = "1y".to_string();
+        assert_eq!(problems(&config).len(), 2);
+    }
+
+    #[test]
+    fn gang_size_buckets_must_increase_from_one() {
+        let mut config = valid_config();
+        config.stats.gang_size_buckets = vec![1];
+        assert!(problems(&config).is_empty());
+        config.stats.gang_size_buckets = vec![0, 5];
+        assert_eq!(problems(&config).len(), 1);
+        config.stats.gang_size_buckets = vec![1, 5, 5];
+        assert_eq!(problems(&config).len(), 1);
+        config.stats.gang_size_buckets = Vec::new();
+        assert_eq!(problems(&config).len(), 1);
+    }
+
+    #[test]
+    fn all_time_window_contains_everything() {
+        let all = StatsWindow::new("all", None);
+        assert_eq!(all.start_time(), None);
+        assert!(all.contains(NaiveDateTime::from_timestamp(0, 0)));
+        let day = StatsWindow::new("24h", Some(24));
+        assert!(!day.contains(Utc::now().naive_utc() - Duration::hours(25)));
+        assert!(day.contains(Utc::now().naive_utc() - Duration::hours(23)));
+    }
+}
@@ -0,0 +1,151 @@
+use crate::config::StatsWindow;
+use crate::organization_processing::OrganizationKind;
+use crate::stats_processing;
+use crate::stats_processing::push_window_filter;
+use schemars::JsonSchema;
+use sea_orm::{DatabaseConnection, DbBackend, DbErr, FromQueryResult, Statement, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How much a pilot or organization actually contributes to its kills
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+pub struct DamageStats {
+    pub final_blows: usize,
+    /// Kills where nobody else did more damage
+    pub top_damage: usize,
+    /// Average share of the victim's damage taken, from 0 to 1
+    pub average_damage_share: Option<f64>,
+}
+
+/// The victim's damage taken on attacker `a`'s killmail, or what the attackers
+/// did when the victim has none recorded
+const DAMAGE_TAKEN_SQL: &str = r#"COALESCE(NULLIF(v.damage_taken, 0),
+                (SELECT SUM(m.damage_done) FROM attackers m WHERE m.killmail_id = a.killmail_id))"#;
+
+#[derive(Debug, FromQueryResult)]
+struct DamageTotals {
+    final_blows: i64,
+    top_damage: i64,
+    average_damage_share: Option<f64>,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct CharacterDamageTotals {
+    character_id: u64,
+    final_blows: i64,
+    top_damage: i64,
+    average_damage_share: Option<f64>,
+}
+
+/// Damage stats for each character on their kills inside `window`. Victims
+/// without damage taken fall back to what the attackers did.
+pub async fn get_character_damage_stats(
+    db: &DatabaseConnection,
+    character_ids: &[u64],
+    window: &StatsWindow,
+) -> Result<HashMap<u64, DamageStats>, DbErr> {
+    if character_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let mut values: Vec<Value> = Vec::new();
+    let mut sql = format!(
+        r#"SELECT a.character_id, a.killmail_id,
+            MAX(a.final_blow) AS final_blow,
+            SUM(a.damage_done) AS damage_done,
+            MAX(a.damage_done) AS own_top_damage,
+            (SELECT MAX(m.damage_done) FROM attackers m WHERE m.killmail_id = a.killmail_id)
+                AS top_damage,
+            {} AS damage_taken
+        FROM attackers a
+        JOIN victims v ON v.killmail_id = a.killmail_id
+        JOIN killmails k ON k.killmail_id = a.killmail_id
+        WHERE a.character_id IN ({})"#,
+        DAMAGE_TAKEN_SQL,
+        stats_processing::push_id_list(&mut values, character_ids)
+    );
+    push_window_filter(&mut sql, &mut values, window);
+    sql.push_str(" GROUP BY a.character_id, a.killmail_id, v.damage_taken");
+    let sql = format!(
+        r#"SELECT d.character_id AS character_id,
+            CAST(COALESCE(SUM(d.final_blow), 0) AS SIGNED) AS final_blows,
+            CAST(COALESCE(SUM(d.own_top_damage > 0 AND d.own_top_damage = d.top_damage), 0)
+                AS SIGNED) AS top_damage,
+            CAST(AVG(d.damage_done / NULLIF(d.damage_taken, 0)) AS DOUBLE) AS average_damage_share
+        FROM ({}) d
+        GROUP BY d.character_id"#,
+        sql
+    );
+    Ok(
+        CharacterDamageTotals::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::MySql,
+            &sql,
+            values,
+        ))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|totals| {
+            (
+                totals.character_id,
+                DamageStats {
+                    final_blows: totals.final_blows as usize,
+                    top_damage: totals.top_damage as usize,
+                    average_damage_share: totals.average_damage_share,
+                },
+            )
+        })
+        .collect(),
+    )
+}
+
+/// Damage stats for a corporation or alliance, treating all members on a
+/// killmail as one attacker. Victim damage taken falls back the same way as
+/// for characters.
+pub async fn get_organization_damage_stats(
+    db: &DatabaseConnection,
+    kind: OrganizationKind,
+    organization_id: u64,
+    window: &StatsWindow,
+) -> Result<DamageStats, DbErr> {
+    let mut sql = format!(
+        r#"SELECT a.killmail_id,
+            MAX(a.final_blow) AS final_blow,
+            SUM(a.damage_done) AS damage_done,
+            MAX(a.damage_done) AS member_top_damage,
+            (SELECT MAX(m.damage_done) FROM attackers m WHERE m.killmail_id = a.killmail_id)
+                AS top_damage,
+            {damage_taken} AS damage_taken
+        FROM attackers a
+        JOIN victims v ON v.killmail_id = a.killmail_id
+        JOIN killmails k ON k.killmail_id = a.killmail_id
+        WHERE a.{column} = ?"#,
+        damage_taken = DAMAGE_TAKEN_SQL,
+        column = kind.column_name()
+    );
+    let mut values: Vec<Value> = vec![organization_id.into()];
+    push_window_filter(&mut sql, &mut values, window);
+    sql.push_str(" GROUP BY a.killmail_id, v.damage_taken");
+    let sql = format!(
+        r#"SELECT
+            CAST(COALESCE(SUM(d.final_blow), 0) AS SIGNED) AS final_blows,
+            CAST(COALESCE(SUM(d.member_top_damage > 0 AND d.member_top_damage = d.top_damage), 0)
+                AS SIGNED) AS top_damage,
+            CAST(AVG(d.damage_done / NULLIF(d.damage_taken, 0)) AS DOUBLE) AS average_damage_share
+        FROM ({}) d"#,
+        sql
+    );
+    let totals = DamageTotals::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::MySql,
+        &sql,
+        values,
+    ))
+    .one(db)
+    .await?;
+    Ok(totals
+        .map(|totals| DamageStats {
+            final_blows: totals.final_blows as usize,
+            top_damage: totals.top_damage as usize,
+            average_damage_share: totals.average_damage_share,
+        })
+        .unwrap_or_default())
+}
@@ -1,13 +1,13 @@
+use crate::config;
+use crate::config::DatabaseConfig;
 use async_trait::async_trait;
-use dotenv::dotenv;
 use futures::{stream, StreamExt};
 use sea_orm::{
     ActiveModelBehavior, ActiveModelTrait, ActiveValue, ConnectOptions, Database,
     DatabaseConnection, DbErr, EntityTrait, IntoActiveModel, Value,
 };
+use sea_orm_rocket::rocket::figment::Figment;
 use sea_orm_rocket::Database as SODatabase;
-use sea_orm_rocket::{rocket::figment::Figment, Config};
-use std::env;
 use std::fmt;
 use tokio::time::{sleep, Duration};
 
@@ -26,17 +26,8 @@ impl sea_orm_rocket::Pool for SeaOrmPool {
 
     type Connection = sea_orm::DatabaseConnection;
 
-    async fn init(figment: &Figment) -> Result<Self, Self::Error> {
-        dotenv().ok();
-        let config = figment.extract::<Config>().unwrap();
-        let mut opt = ConnectOptions::new(config.url);
-        opt.max_connections(100)
-            .min_connections(10)
-            .connect_timeout(Duration::from_secs(8))
-            .idle_timeout(Duration::from_secs(8))
-            .max_lifetime(Duration::from_secs(8))
-            .sqlx_logging(true);
-        let conn = sea_orm::Database::connect(opt).await?;
+    async fn init(_figment: &Figment) -> Result<Self, Self::Error> {
+        let conn = sea_orm::Database::connect(get_connect_options(&config::get().database)).await?;
         Ok(SeaOrmPool { conn })
     }
     fn borrow(&self) -> &Self::Connection {
@@ -79,18 +70,20 @@ impl From<ValueUnwrapError> for JagerDatabaseError {
     }
 }
 
-/// Get a database connection from environment variable
+fn get_connect_options(database_config: &DatabaseConfig) -> ConnectOptions {
+    let mut opt = ConnectOptions::new(database_config.url.to_owned());
+    opt.max_connections(database_config.max_connections)
+        .min_connections(database_config.min_connections)
+        .connect_timeout(Duration::from_secs(database_config.connect_timeout_secs))
+        .idle_timeout(Duration::from_secs(database_config.idle_timeout_secs))
+        .max_lifetime(Duration::from_secs(database_config.max_lifetime_secs))
+        .sqlx_logging(database_config.sqlx_logging);
+    opt
+}
+
+/// Get a database connection using the database section of the config
 pub async fn establish_connection() -> Result<DatabaseConnection, DbErr> {
-    dotenv().ok();
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL not found.");
-    let mut opt = ConnectOptions::new(database_url.to_owned());
-    opt.max_connections(1)
-        .min_connections(1)
-        .connect_timeout(Duration::from_secs(8))
-        .idle_timeout(Duration::from_secs(8))
-        .max_lifetime(Duration::from_secs(8))
-        .sqlx_logging(true);
-    Ok(Database::connect(opt).await?)
+    Ok(Database::connect(get_connect_options(&config::get().database)).await?)
 }
 
 pub async fn try_establish_connection() -> Result<DatabaseConnection, DbErr> {
@@ -360,7 +353,7 @@ pub async fn insert_corporations_if_not_present(
     let mut error: Option<JagerDatabaseError> = Option::None;
     let mut bodies = stream::iter(corporations)
         .map(|corp| async move { insert_corporation_if_not_present(db, corp).await })
-        .buffer_unordered(10);
+        .buffer_unordered(config::get().concurrency.database_inserts);
     while let Some(result) = bodies.next().await {
         match result {
             Ok(_) => info!("Inserted corporation"),
@@ -381,7 +374,7 @@ pub async fn insert_pubchars_if_not_present(
     let mut error: Option<JagerDatabaseError> = Option::None;
     let mut bodies = stream::iter(public_infos)
         .map(|pubinfo| async move { insert_pubchar_info_if_not_present(db, pubinfo).await })
-        .buffer_unordered(10);
+        .buffer_unordered(config::get().concurrency.database_inserts);
     while let Some(result) = bodies.next().await {
         match result {
             Ok(_) => info!("Inserted attacker"),
@@ -402,7 +395,7 @@ pub async fn insert_alliances_if_not_present(
     let mut error: Option<JagerDatabaseError> = Option::None;
     let mut bodies = stream::iter(alliances)
         .map(|alliance| async move { insert_alliance_if_not_present(db, alliance).await })
-        .buffer_unordered(10);
+        .buffer_unordered(config::get().concurrency.database_inserts);
     while let Some(result) = bodies.next().await {
         match result {
             Ok(_) => info!("Inserted alliance"),
@@ -478,7 +471,7 @@ pub async fn insert_if_not_present_types(
 ) {
     let mut inserts = stream::iter(esi_types)
         .map(|item| insert_type_if_not_present(db, item))
-        .buffer_unordered(10);
+        .buffer_unordered(config::get().concurrency.database_inserts);
     while let Some(res) = inserts.next().await {
         match res {
             Ok(result) => info!("Processed: {:?}", result),
@@ -493,7 +486,7 @@ pub async fn insert_if_not_present_groups(
 ) {
     let mut inserts = stream::iter(groups)
         .map(|item| insert_group_if_not_present(db, item))
-        .buffer_unordered(10);
+        .buffer_unordered(config::get().concurrency.database_inserts);
     while let Some(res) = inserts.next().await {
         match res {
             Ok(result) => info!("Processed {:?}", result),
@@ -508,7 +501,7 @@ pub async fn insert_if_not_present_factions(
 ) {
     let mut inserts = stream::iter(factions)
         .map(|item| insert_faction_if_not_present(db, item))
-        .buffer_unordered(10);
+        .buffer_unordered(config::get().concurrency.database_inserts);
     while let Some(res) = inserts.next().await {
         match res {
             Ok(_) => {}
@@ -523,7 +516,7 @@ pub async fn insert_if_not_present_categories(
 ) {
     let mut inserts = stream::iter(categories)
         .map(|item| insert_category_if_not_present(db, item))
-        .buffer_unordered(10);
+        .buffer_unordered(config::get().concurrency.database_inserts);
     while let Some(res) = inserts.next().await {
         match res {
             Ok(result) => info!("Processed {:?}", result),
@@ -541,3 +534,21 @@ pub async fn insert_multiple_attackers(
         .await?;
     Ok(())
 }
+
+/// Insert rows, leaving any that already exist alone
+pub async fn insert_if_not_present<T>(db: &DatabaseConnection, items: Vec<T>, label: &str)
+where
+    <<T as ActiveModelTrait>::Entity as EntityTrait>::Model: IntoActiveModel<T>,
+    T: ActiveModelTrait + ActiveModelBehavior + Send,
+{
+    let mut inserts = stream::iter(items)
+        .map(|item| insert_retry(db, item))
+        .buffer_unordered(config::get().concurrency.database_inserts);
+    while let Some(res) = inserts.next().await {
+        match res {
+            Ok(_) => {}
+            Err(e) if is_duplicate_err(&e) => {}
+            Err(e) => error!("Got error {:?} while storing {}", e, label),
+        }
+    }
+}
@@ -28,6 +28,10 @@ pub enum Relation {
     Attackers,
     #[sea_orm(has_one = "super::victims::Entity")]
     Victims,
+    #[sea_orm(has_many = "super::character_daily_stats::Entity")]
+    CharacterDailyStats,
+    #[sea_orm(has_many = "super::character_name_history::Entity")]
+    CharacterNameHistory,
 }
 
 impl Related<super::attackers::Entity> for Entity {
@@ -42,6 +46,18 @@ impl Related<super::victims::Entity> for Entity {
     }
 }
 
+impl Related<super::character_daily_stats::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CharacterDailyStats.def()
+    }
+}
+
+impl Related<super::character_name_history::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CharacterNameHistory.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
 
 impl ActiveModel {
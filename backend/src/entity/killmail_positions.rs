@@ -3,8 +3,9 @@
 use datamodels::esi_models::ESIKillPosition;
 use sea_orm::entity::prelude::*;
 use sea_orm::{NotSet, Set};
+use serde::Serialize;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
 #[sea_orm(table_name = "killmail_positions")]
 pub struct Model {
     #[sea_orm(primary_key)]
@@ -3,14 +3,16 @@
 use datamodels::esi_models::ESIKillmail;
 use sea_orm::entity::prelude::*;
 use sea_orm::Set;
+use serde::Serialize;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
 #[sea_orm(table_name = "killmails")]
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub killmail_id: u64,
     pub killmail_time: DateTime,
     pub solar_system_id: u64,
+    pub awox: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -19,6 +21,10 @@ pub enum Relation {
     Attackers,
     #[sea_orm(has_many = "super::killmail_positions::Entity")]
     KillmailPositions,
+    #[sea_orm(has_one = "super::killmail_values::Entity")]
+    KillmailValues,
+    #[sea_orm(has_many = "super::victim_items::Entity")]
+    VictimItems,
     #[sea_orm(has_many = "super::victims::Entity")]
     Victims,
 }
@@ -35,6 +41,18 @@ impl Related<super::killmail_positions::Entity> for Entity {
     }
 }
 
+impl Related<super::killmail_values::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::KillmailValues.def()
+    }
+}
+
+impl Related<super::victim_items::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::VictimItems.def()
+    }
+}
+
 impl Related<super::victims::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Victims.def()
@@ -49,6 +67,7 @@ impl From<ESIKillmail> for ActiveModel {
             killmail_id: Set(item.killmail_id),
             killmail_time: Set(item.killmail_time),
             solar_system_id: Set(item.solar_system_id),
+            awox: Set(crate::awox_processing::is_awox_killmail(&item)),
         }
     }
 }
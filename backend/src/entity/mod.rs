@@ -4,12 +4,20 @@ pub mod prelude;
 
 pub mod alliances;
 pub mod attackers;
+pub mod character_daily_stats;
+pub mod character_name_history;
 pub mod character_public_info;
+pub mod constellations;
 pub mod corporations;
 pub mod esi_categories;
 pub mod esi_groups;
 pub mod esi_types;
 pub mod factions;
 pub mod killmail_positions;
+pub mod killmail_values;
 pub mod killmails;
+pub mod market_prices;
+pub mod regions;
+pub mod solar_systems;
+pub mod victim_items;
 pub mod victims;
@@ -2,12 +2,20 @@
 
 pub use super::alliances::Entity as Alliances;
 pub use super::attackers::Entity as Attackers;
+pub use super::character_daily_stats::Entity as CharacterDailyStats;
+pub use super::character_name_history::Entity as CharacterNameHistory;
 pub use super::character_public_info::Entity as CharacterPublicInfo;
+pub use super::constellations::Entity as Constellations;
 pub use super::corporations::Entity as Corporations;
 pub use super::esi_categories::Entity as EsiCategories;
 pub use super::esi_groups::Entity as EsiGroups;
 pub use super::esi_types::Entity as EsiTypes;
 pub use super::factions::Entity as Factions;
 pub use super::killmail_positions::Entity as KillmailPositions;
+pub use super::killmail_values::Entity as KillmailValues;
 pub use super::killmails::Entity as Killmails;
+pub use super::market_prices::Entity as MarketPrices;
+pub use super::regions::Entity as Regions;
+pub use super::solar_systems::Entity as SolarSystems;
+pub use super::victim_items::Entity as VictimItems;
 pub use super::victims::Entity as Victims;
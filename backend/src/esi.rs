@@ -1,20 +1,17 @@
+use crate::config;
 use datamodels::esi_models::*;
 use futures::{stream, StreamExt};
 use reqwest;
+use serde::de::DeserializeOwned;
 use std::str;
 use tokio;
 use tokio::time::{sleep, Duration};
 
-const ESI_URL: &str = "https://esi.evetech.net";
-const ESI_VERSION: &str = "latest";
-const DATASOURCE: &str = "tranquility";
-const RETRY_COUNT: u64 = 10;
-const CONCURRENCY: usize = 100;
-
 pub fn get_uri(path: String) -> String {
+    let esi_config = &config::get().esi;
     format!(
         "{}/{}/{}/?datasource={}",
-        ESI_URL, ESI_VERSION, path, DATASOURCE
+        esi_config.url, esi_config.version, path, esi_config.datasource
     )
 }
 
@@ -24,6 +21,18 @@ pub enum EsiError {
     Parse(serde_json::Error),
 }
 
+impl EsiError {
+    /// ESI answers 420 once we've used up the error limit, 429 otherwise
+    pub fn is_rate_limited(&self) -> bool {
+        match self {
+            EsiError::ApiError(e) => {
+                matches!(e.status().map(|status| status.as_u16()), Some(420 | 429))
+            }
+            EsiError::Parse(_) => false,
+        }
+    }
+}
+
 impl From<reqwest::Error> for EsiError {
     fn from(err: reqwest::Error) -> EsiError {
         EsiError::ApiError(err)
@@ -78,7 +87,7 @@ pub async fn get_paginated_esi_list_results(uri_path: String) -> Result<Vec<u64>
                 let res: Vec<u64> = reqwest::get(url).await.unwrap().json().await.unwrap();
                 res
             })
-            .buffer_unordered(CONCURRENCY);
+            .buffer_unordered(config::get().esi.concurrency);
         while let Some(mut item) = bodies.next().await {
             results.append(&mut item);
         }
@@ -98,7 +107,7 @@ pub async fn get_esi_categories(category_ids: Vec<u64>) -> Vec<ESICategory> {
             let res: Result<String, EsiError> = get_text_retry(url).await;
             res
         })
-        .buffer_unordered(CONCURRENCY);
+        .buffer_unordered(config::get().esi.concurrency);
     while let Some(item) = bodies.next().await {
         match item {
             Ok(text) => {
@@ -126,7 +135,7 @@ pub async fn get_esi_groups(category_ids: Vec<u64>) -> Vec<ESIGroup> {
             let res: Result<String, EsiError> = get_text_retry(url).await;
             res
         })
-        .buffer_unordered(CONCURRENCY);
+        .buffer_unordered(config::get().esi.concurrency);
     while let Some(item) = bodies.next().await {
         match item {
             Ok(text) => {
@@ -154,7 +163,7 @@ pub async fn get_esi_types(type_ids: Vec<u64>) -> Vec<ESIType> {
             let res: Result<String, EsiError> = get_text_retry(url).await;
             res
         })
-        .buffer_unordered(CONCURRENCY);
+        .buffer_unordered(config::get().esi.concurrency);
     while let Some(item) = bodies.next().await {
         match item {
             Ok(text) => {
@@ -170,6 +179,58 @@ pub async fn get_esi_types(type_ids: Vec<u64>) -> Vec<ESIType> {
     results
 }
 
+/// Fetch and deserialize one object per path, skipping any that fail
+async fn get_esi_objects<T: DeserializeOwned>(paths: Vec<String>, label: &str) -> Vec<T> {
+    let mut results: Vec<T> = Vec::new();
+    let request_urls: Vec<String> = paths.into_iter().map(get_uri).collect();
+    let mut bodies = stream::iter(request_urls)
+        .map(|url| async move {
+            info!("Sending request to {}", url);
+            let res: Result<String, EsiError> = get_text_retry(url).await;
+            res
+        })
+        .buffer_unordered(config::get().esi.concurrency);
+    while let Some(item) = bodies.next().await {
+        match item {
+            Ok(text) => match serde_json::from_str::<T>(&text) {
+                Ok(object) => results.push(object),
+                Err(e) => error!("Couldn't deserialize {}: {}", label, e),
+            },
+            Err(e) => error!("Couldn't fetch {}: {:?}", label, e),
+        }
+    }
+    results
+}
+
+pub async fn get_region_list() -> Result<Vec<u64>, EsiError> {
+    let results = get_paginated_esi_list_results("universe/regions".to_string()).await?;
+    Ok(results)
+}
+
+pub async fn get_esi_regions(region_ids: Vec<u64>) -> Vec<ESIRegion> {
+    let paths = region_ids
+        .into_iter()
+        .map(|id| format!("universe/regions/{}", id))
+        .collect();
+    get_esi_objects(paths, "region").await
+}
+
+pub async fn get_esi_constellations(constellation_ids: Vec<u64>) -> Vec<ESIConstellation> {
+    let paths = constellation_ids
+        .into_iter()
+        .map(|id| format!("universe/constellations/{}", id))
+        .collect();
+    get_esi_objects(paths, "constellation").await
+}
+
+pub async fn get_esi_solar_systems(system_ids: Vec<u64>) -> Vec<ESISolarSystem> {
+    let paths = system_ids
+        .into_iter()
+        .map(|id| format!("universe/systems/{}", id))
+        .collect();
+    get_esi_objects(paths, "solar system").await
+}
+
 pub async fn get_character(
     character_id: u64,
 ) -> Result<crate::entity::character_public_info::ActiveModel, EsiError> {
@@ -209,7 +270,7 @@ pub async fn get_characters(
     let request_urls: Vec<u64> = character_ids;
     let mut bodies = stream::iter(request_urls)
         .map(|id| async move { get_character(id).await })
-        .buffer_unordered(CONCURRENCY);
+        .buffer_unordered(config::get().esi.concurrency);
     while let Some(item) = bodies.next().await {
         match item {
             Ok(char) => {
@@ -236,7 +297,7 @@ pub async fn get_alliances(
     let request_urls: Vec<u64> = alliance_ids;
     let mut bodies = stream::iter(request_urls)
         .map(|id| async move { get_alliance(id).await })
-        .buffer_unordered(CONCURRENCY);
+        .buffer_unordered(config::get().esi.concurrency);
     while let Some(item) = bodies.next().await {
         match item {
             Ok(alliance) => {
@@ -263,7 +324,7 @@ pub async fn get_corporations(
     let request_urls: Vec<u64> = corporation_ids;
     let mut bodies = stream::iter(request_urls)
         .map(|id| async move { get_corporation(id).await })
-        .buffer_unordered(CONCURRENCY);
+        .buffer_unordered(config::get().esi.concurrency);
     while let Some(item) = bodies.next().await {
         match item {
             Ok(corporation) => {
@@ -361,7 +422,8 @@ pub async fn get_killmail(req: &ESIKillmailRequest) -> Result<ESIKillmail, EsiEr
 }
 
 async fn get_text_retry(url: String) -> Result<String, EsiError> {
-    let mut retry_attempts = RETRY_COUNT;
+    let retry_count = config::get().esi.retry_count;
+    let mut retry_attempts = retry_count;
     let result: Result<String, EsiError> = loop {
         match get_text(&url).await {
             Ok(result) => {
@@ -370,7 +432,7 @@ async fn get_text_retry(url: String) -> Result<String, EsiError> {
             Err(e) => {
                 if retry_attempts > 0 {
                     retry_attempts -= 1;
-                    let retry_delay = (11 - retry_attempts) * 200;
+                    let retry_delay = (retry_count + 1 - retry_attempts) * 200;
                     warn!(
                         "Got error {:?} while fetching {}, retrying in at least {}ms ({} attempts remain)",
                         e, &url, retry_delay, retry_attempts
@@ -386,6 +448,13 @@ async fn get_text_retry(url: String) -> Result<String, EsiError> {
     result
 }
 
+pub async fn get_market_prices() -> Result<Vec<ESIMarketPrice>, EsiError> {
+    let request_uri = get_uri("markets/prices".to_string());
+    let text_result = get_text_retry(request_uri).await?;
+    let results: Vec<ESIMarketPrice> = serde_json::from_str(&text_result)?;
+    Ok(results)
+}
+
 pub async fn get_factions() -> Result<Vec<ESIFaction>, EsiError> {
     let request_uri = get_uri("universe/factions".to_string());
     let text_result = get_text_retry(request_uri).await?;
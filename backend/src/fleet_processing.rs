@@ -0,0 +1,333 @@
+use crate::config;
+use crate::config::StatsWindow;
+use crate::name_processing;
+use crate::name_processing::NameLookup;
+use crate::role_processing;
+use crate::role_processing::RoleTag;
+use crate::ship_processing;
+use chrono::NaiveDateTime;
+use schemars::JsonSchema;
+use sea_orm::{DatabaseConnection, DbBackend, DbErr, FromQueryResult, Statement, Value};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Roles that mean a group can bring more than what's in local
+const WARNING_ROLES: &[RoleTag] = &[RoleTag::Cyno, RoleTag::HotdropRisk, RoleTag::Capital];
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct FleetMember {
+    pub character_id: u64,
+    pub name: String,
+    pub kills: usize,
+    pub losses: usize,
+    pub last_seen: NaiveDateTime,
+    /// The ship on the pilot's most recent killmail
+    pub last_ship_type_id: Option<u64>,
+    pub last_ship_name: Option<String>,
+    /// Roles of any ship the pilot flew or lost in the window
+    pub roles: Vec<RoleTag>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct RoleCount {
+    pub role: RoleTag,
+    pub pilots: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct ThreatSummary {
+    pub pilots: usize,
+    /// Killmails any member was on
+    pub kills: usize,
+    pub losses: usize,
+    /// Killmails two or more members were on together
+    pub shared_kills: usize,
+    pub last_seen: NaiveDateTime,
+    /// Roles present in the group that could bring reinforcements
+    pub warnings: Vec<RoleTag>,
+}
+
+/// Pilots that have been killing together
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct Fleet {
+    pub members: Vec<FleetMember>,
+    pub composition: Vec<RoleCount>,
+    pub threat: ThreatSummary,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct LocalAnalysis {
+    pub window: String,
+    /// Largest fleets first
+    pub fleets: Vec<Fleet>,
+    /// Whether the pilots had more killmails in the window than are read, in
+    /// which case only the newest were used
+    pub truncated: bool,
+    /// Known pilots with no killmails in the window
+    pub inactive: Vec<String>,
+    pub unknown: Vec<String>,
+    /// Names held by more than one character
+    pub ambiguous: Vec<String>,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct Sighting {
+    killmail_id: u64,
+    character_id: u64,
+    ship_type_id: Option<u64>,
+    killmail_time: NaiveDateTime,
+    is_loss: i64,
+}
+
+/// The killmails in the window the pilots were on, as attackers or victims,
+/// newest first and at most `stats.local_max_sightings` of them
+async fn get_sightings(
+    db: &DatabaseConnection,
+    character_ids: &[u64],
+    window: &StatsWindow,
+) -> Result<Vec<Sighting>, DbErr> {
+    let placeholders = vec!["?"; character_ids.len()].join(", ");
+    let mut parts: Vec<String> = Vec::with_capacity(2);
+    let mut values: Vec<Value> = Vec::with_capacity(character_ids.len() * 2 + 3);
+    for (table, is_loss) in [("attackers", 0), ("victims", 1)] {
+        let mut sql = format!(
+            r#"SELECT s.killmail_id, s.character_id, s.ship_type_id, k.killmail_time,
+                CAST({is_loss} AS SIGNED) AS is_loss
+            FROM {table} s
+            JOIN killmails k ON k.killmail_id = s.killmail_id
+            WHERE s.character_id IN ({placeholders})"#,
+            is_loss = is_loss,
+            table = table,
+            placeholders = placeholders
+        );
+        values.extend(
+            character_ids
+                .iter()
+                .map(|character_id| (*character_id).into()),
+        );
+        if let Some(start_time) = window.start_time() {
+            sql.push_str(" AND k.killmail_time >= ?");
+            values.push(start_time.into());
+        }
+        parts.push(sql);
+    }
+    let sql = format!(
+        "SELECT * FROM ({}) s ORDER BY s.killmail_time DESC, s.killmail_id DESC LIMIT ?",
+        parts.join(" UNION ALL ")
+    );
+    values.push(config::get().stats.local_max_sightings.into());
+    Sighting::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::MySql,
+        &sql,
+        values,
+    ))
+    .all(db)
+    .await
+}
+
+fn find_root(parents: &mut HashMap<u64, u64>, id: u64) -> u64 {
+    let parent = parents[&id];
+    if parent == id {
+        return id;
+    }
+    let root = find_root(parents, parent);
+    parents.insert(id, root);
+    root
+}
+
+fn build_fleet(members: Vec<FleetMember>, killmails: &HashMap<u64, Vec<u64>>) -> Fleet {
+    let member_ids: HashSet<u64> = members.iter().map(|member| member.character_id).collect();
+    let mut kills: usize = 0;
+    let mut shared_kills: usize = 0;
+    for attackers in killmails.values() {
+        match attackers
+            .iter()
+            .filter(|id| member_ids.contains(id))
+            .count()
+        {
+            0 => {}
+            1 => kills += 1,
+            _ => {
+                kills += 1;
+                shared_kills += 1;
+            }
+        }
+    }
+    let mut role_counts: HashMap<RoleTag, usize> = HashMap::new();
+    for role in members.iter().flat_map(|member| member.roles.iter()) {
+        *role_counts.entry(*role).or_insert(0) += 1;
+    }
+    let mut composition: Vec<RoleCount> = role_counts
+        .into_iter()
+        .map(|(role, pilots)| RoleCount { role, pilots })
+        .collect();
+    composition.sort_by_key(|count| Reverse(count.pilots));
+    let warnings: Vec<RoleTag> = WARNING_ROLES
+        .iter()
+        .filter(|role| composition.iter().any(|count| count.role == **role))
+        .cloned()
+        .collect();
+    let threat = ThreatSummary {
+        pilots: members.len(),
+        kills,
+        losses: members.iter().map(|member| member.losses).sum(),
+        shared_kills,
+        last_seen: members
+            .iter()
+            .map(|member| member.last_seen)
+            .max()
+            .expect("fleets have at least one member"),
+        warnings,
+    };
+    Fleet {
+        members,
+        composition,
+        threat,
+    }
+}
+
+/// Group the pilots in a pasted local into likely fleets. Pilots are put in
+/// the same fleet when they were on enough of the same kills in the window,
+/// directly or through someone else in local.
+pub async fn analyze_local(
+    db: &DatabaseConnection,
+    names: Vec<String>,
+    window: &StatsWindow,
+) -> Result<LocalAnalysis, DbErr> {
+    let lookups = name_processing::lookup_character_names(db, &names).await?;
+    let mut pilots: BTreeMap<u64, String> = BTreeMap::new();
+    let mut unknown: Vec<String> = Vec::new();
+    let mut ambiguous: Vec<String> = Vec::new();
+    for name in names {
+        match lookups.get(&name.to_lowercase()) {
+            Some(NameLookup::Found(character_id)) => {
+                pilots.insert(*character_id, name);
+            }
+            Some(NameLookup::Ambiguous(_)) => ambiguous.push(name),
+            _ => unknown.push(name),
+        }
+    }
+    let character_ids: Vec<u64> = pilots.keys().cloned().collect();
+    let sightings = if character_ids.is_empty() {
+        Vec::new()
+    } else {
+        get_sightings(db, &character_ids, window).await?
+    };
+    let truncated = sightings.len() as u64 >= config::get().stats.local_max_sightings;
+    let mut type_ids: Vec<u64> = sightings
+        .iter()
+        .filter_map(|sighting| sighting.ship_type_id)
+        .collect();
+    type_ids.sort_unstable();
+    type_ids.dedup();
+    let type_info = ship_processing::get_type_info(db, type_ids).await?;
+    let mut members: HashMap<u64, FleetMember> = HashMap::new();
+    let mut killmails: HashMap<u64, Vec<u64>> = HashMap::new();
+    for sighting in sightings {
+        let name = &pilots[&sighting.character_id];
+        let member = members
+            .entry(sighting.character_id)
+            .or_insert_with(|| FleetMember {
+                character_id: sighting.character_id,
+                name: name.clone(),
+                kills: 0,
+                losses: 0,
+                last_seen: sighting.killmail_time,
+                last_ship_type_id: sighting.ship_type_id,
+                last_ship_name: None,
+                roles: Vec::new(),
+            });
+        if sighting.is_loss == 1 {
+            member.losses += 1;
+        } else {
+            let attackers = killmails.entry(sighting.killmail_id).or_default();
+            if attackers.contains(&sighting.character_id) {
+                continue;
+            }
+            attackers.push(sighting.character_id);
+            member.kills += 1;
+        }
+        if sighting.killmail_time > member.last_seen {
+            member.last_seen = sighting.killmail_time;
+            member.last_ship_type_id = sighting.ship_type_id.or(member.last_ship_type_id);
+        } else if member.last_ship_type_id.is_none() {
+            member.last_ship_type_id = sighting.ship_type_id;
+        }
+        let esi_type = sighting
+            .ship_type_id
+            .and_then(|ship_type_id| type_info.get(&ship_type_id));
+        if let Some((esi_type, _)) = esi_type {
+            for role in role_processing::get_roles(esi_type.group_id) {
+                if !member.roles.contains(&role) {
+                    member.roles.push(role);
+                }
+            }
+        }
+    }
+    for member in members.values_mut() {
+        member.last_ship_name = member
+            .last_ship_type_id
+            .and_then(|ship_type_id| type_info.get(&ship_type_id))
+            .map(|(esi_type, _)| esi_type.type_name.clone());
+    }
+    let mut pair_counts: HashMap<(u64, u64), usize> = HashMap::new();
+    for attackers in killmails.values() {
+        for (index, first) in attackers.iter().enumerate() {
+            for second in attackers[index + 1..].iter() {
+                let pair = (*first.min(second), *first.max(second));
+                *pair_counts.entry(pair).or_insert(0) += 1;
+            }
+        }
+    }
+    let mut parents: HashMap<u64, u64> = members.keys().map(|id| (*id, *id)).collect();
+    let min_shared = config::get().stats.fleet_min_shared_killmails;
+    for ((first, second), count) in pair_counts {
+        if count >= min_shared {
+            let first_root = find_root(&mut parents, first);
+            let second_root = find_root(&mut parents, second);
+            parents.insert(first_root, second_root);
+        }
+    }
+    let mut groups: HashMap<u64, Vec<FleetMember>> = HashMap::new();
+    let member_ids: Vec<u64> = members.keys().cloned().collect();
+    for character_id in member_ids {
+        let root = find_root(&mut parents, character_id);
+        if let Some(member) = members.remove(&character_id) {
+            groups.entry(root).or_default().push(member);
+        }
+    }
+    let mut fleets: Vec<Fleet> = groups
+        .into_values()
+        .map(|mut group| {
+            group.sort_by_key(|member| Reverse(member.kills + member.losses));
+            build_fleet(group, &killmails)
+        })
+        .collect();
+    fleets.sort_by(|a, b| {
+        b.threat
+            .pilots
+            .cmp(&a.threat.pilots)
+            .then(b.threat.kills.cmp(&a.threat.kills))
+    });
+    let inactive: Vec<String> = pilots
+        .iter()
+        .filter(|(character_id, _)| {
+            !fleets.iter().any(|fleet| {
+                fleet
+                    .members
+                    .iter()
+                    .any(|member| member.character_id == **character_id)
+            })
+        })
+        .map(|(_, name)| name.clone())
+        .collect();
+    Ok(LocalAnalysis {
+        window: window.name.clone(),
+        fleets,
+        truncated,
+        inactive,
+        unknown,
+        ambiguous,
+    })
+}
@@ -0,0 +1,313 @@
+use crate::config;
+use crate::config::StatsWindow;
+use crate::stats_processing;
+use crate::stats_processing::push_window_filter;
+use schemars::JsonSchema;
+use sea_orm::{DatabaseConnection, DbBackend, DbErr, FromQueryResult, Statement, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// (gang size, killmails) histograms for a character's kills and losses
+type KillLossGangSizes = (Vec<(usize, usize)>, Vec<(usize, usize)>);
+
+/// How a pilot usually fights, judged from the median gang size on their kills
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EngagementStyle {
+    SoloHunter,
+    SmallGang,
+    Fleet,
+    Blob,
+}
+
+/// Kills and losses with a number of player attackers between `min` and `max`
+/// inclusive. The first bucket holds killmails without player attackers and
+/// the last bucket has no `max`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct GangSizeBucket {
+    pub label: String,
+    pub min: usize,
+    pub max: Option<usize>,
+    pub kills: usize,
+    pub losses: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct GangSizeSummary {
+    pub average: f64,
+    pub median: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+pub struct GangProfile {
+    pub buckets: Vec<GangSizeBucket>,
+    /// Killmails without player attackers are left out of the summaries
+    pub kills: Option<GangSizeSummary>,
+    pub losses: Option<GangSizeSummary>,
+    pub style: Option<EngagementStyle>,
+}
+
+/// Buckets for the configured edges, after a first bucket for killmails
+/// without any player attackers, such as losses to NPCs
+fn get_buckets(edges: &[usize]) -> Vec<GangSizeBucket> {
+    let mut buckets: Vec<GangSizeBucket> = Vec::with_capacity(edges.len() + 2);
+    let mut min = 0;
+    for edge in std::iter::once(&0).chain(edges) {
+        let label = if min == *edge {
+            edge.to_string()
+        } else {
+            format!("{}-{}", min, edge)
+        };
+        buckets.push(GangSizeBucket {
+            label,
+            min,
+            max: Some(*edge),
+            kills: 0,
+            losses: 0,
+        });
+        min = edge + 1;
+    }
+    buckets.push(GangSizeBucket {
+        label: format!("{}+", min),
+        min,
+        max: None,
+        kills: 0,
+        losses: 0,
+    });
+    buckets
+}
+
+/// Index of the bucket `gang_size` falls into in `get_buckets`
+fn get_bucket_index(edges: &[usize], gang_size: usize) -> usize {
+    if gang_size == 0 {
+        return 0;
+    }
+    1 + edges
+        .iter()
+        .position(|edge| gang_size <= *edge)
+        .unwrap_or(edges.len())
+}
+
+/// The `n`th smallest gang size in a histogram sorted by size
+fn nth_gang_size(gang_sizes: &[(usize, usize)], n: usize) -> usize {
+    let mut seen = 0;
+    for (size, count) in gang_sizes {
+        seen += count;
+        if n < seen {
+            return *size;
+        }
+    }
+    gang_sizes.last().map_or(0, |(size, _)| *size)
+}
+
+/// Average and median of a histogram of `(gang size, killmails)`
+fn get_summary(mut gang_sizes: Vec<(usize, usize)>) -> Option<GangSizeSummary> {
+    let count: usize = gang_sizes.iter().map(|(_, count)| count).sum();
+    if count == 0 {
+        return None;
+    }
+    gang_sizes.sort_unstable();
+    let average = gang_sizes
+        .iter()
+        .map(|(size, count)| size * count)
+        .sum::<usize>() as f64
+        / count as f64;
+    let median = if count.is_multiple_of(2) {
+        (nth_gang_size(&gang_sizes, count / 2 - 1) + nth_gang_size(&gang_sizes, count / 2)) as f64
+            / 2.0
+    } else {
+        nth_gang_size(&gang_sizes, count / 2) as f64
+    };
+    Some(GangSizeSummary { average, median })
+}
+
+/// A median of one attacker is solo and the open-ended last bucket is a
+/// blob. The buckets in between are split in two, the smaller half (at least
+/// one bucket) small gangs and the rest fleets.
+fn get_style(edges: &[usize], median: f64) -> EngagementStyle {
+    let gang_size = median.ceil() as usize;
+    if gang_size <= 1 {
+        return EngagementStyle::SoloHunter;
+    }
+    let index = match edges.iter().position(|edge| gang_size <= *edge) {
+        Some(index) => index,
+        None => return EngagementStyle::Blob,
+    };
+    // The bucket holding two attackers is the first that isn't solo
+    let first_gang = edges
+        .iter()
+        .position(|edge| *edge > 1)
+        .unwrap_or(edges.len());
+    let small_gang_buckets = ((edges.len() - first_gang) / 2).max(1);
+    if index < first_gang + small_gang_buckets {
+        EngagementStyle::SmallGang
+    } else {
+        EngagementStyle::Fleet
+    }
+}
+
+#[derive(Debug, FromQueryResult)]
+struct GangSizeCount {
+    character_id: u64,
+    is_loss: i32,
+    players: i64,
+    count: i64,
+}
+
+/// Gang sizes without killmails that had no player attackers, which have a
+/// bucket of their own but would drag the averages down
+fn without_npc_only(gang_sizes: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    gang_sizes
+        .into_iter()
+        .filter(|(size, _)| *size > 0)
+        .collect()
+}
+
+fn get_gang_profile(
+    edges: &[usize],
+    kill_sizes: Vec<(usize, usize)>,
+    loss_sizes: Vec<(usize, usize)>,
+) -> GangProfile {
+    let mut buckets = get_buckets(edges);
+    for (size, count) in kill_sizes.iter() {
+        buckets[get_bucket_index(edges, *size)].kills += count;
+    }
+    for (size, count) in loss_sizes.iter() {
+        buckets[get_bucket_index(edges, *size)].losses += count;
+    }
+    let kills = get_summary(without_npc_only(kill_sizes));
+    let style = kills
+        .as_ref()
+        .map(|summary| get_style(edges, summary.median));
+    GangProfile {
+        buckets,
+        kills,
+        losses: get_summary(without_npc_only(loss_sizes)),
+        style,
+    }
+}
+
+/// Distribution of player attacker counts on each character's kills and
+/// losses inside `window`, bucketed by the configured `stats.gang_size_buckets`
+pub async fn get_gang_profiles(
+    db: &DatabaseConnection,
+    character_ids: &[u64],
+    window: &StatsWindow,
+) -> Result<HashMap<u64, GangProfile>, DbErr> {
+    if character_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let mut values: Vec<Value> = Vec::new();
+    let mut sql = format!(
+        r#"SELECT c.character_id, c.is_loss,
+            (SELECT COUNT(p.character_id) FROM attackers p WHERE p.killmail_id = c.killmail_id)
+                AS players
+        FROM ({}) c
+        JOIN killmails k ON k.killmail_id = c.killmail_id
+        WHERE 1 = 1"#,
+        stats_processing::character_killmails_sql(&mut values, character_ids)
+    );
+    push_window_filter(&mut sql, &mut values, window);
+    let sql = format!(
+        r#"SELECT s.character_id AS character_id, s.is_loss AS is_loss, s.players AS players,
+            COUNT(*) AS count
+        FROM ({}) s
+        GROUP BY s.character_id, s.is_loss, s.players"#,
+        sql
+    );
+    let mut gang_sizes: HashMap<u64, KillLossGangSizes> = HashMap::new();
+    for count in GangSizeCount::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::MySql,
+        &sql,
+        values,
+    ))
+    .all(db)
+    .await?
+    {
+        let (kill_sizes, loss_sizes) = gang_sizes.entry(count.character_id).or_default();
+        let sizes = if count.is_loss == 1 {
+            loss_sizes
+        } else {
+            kill_sizes
+        };
+        sizes.push((count.players as usize, count.count as usize));
+    }
+    let edges = &config::get().stats.gang_size_buckets;
+    Ok(character_ids
+        .iter()
+        .map(|character_id| {
+            let (kill_sizes, loss_sizes) = gang_sizes.remove(character_id).unwrap_or_default();
+            (
+                *character_id,
+                get_gang_profile(edges, kill_sizes, loss_sizes),
+            )
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EDGES: &[usize] = &[1, 5, 15, 50];
+
+    #[test]
+    fn buckets_start_with_npc_only() {
+        let labels: Vec<String> = get_buckets(EDGES)
+            .into_iter()
+            .map(|bucket| bucket.label)
+            .collect();
+        assert_eq!(labels, ["0", "1", "2-5", "6-15", "16-50", "51+"]);
+    }
+
+    #[test]
+    fn bucket_index_at_edges() {
+        assert_eq!(get_bucket_index(EDGES, 0), 0);
+        assert_eq!(get_bucket_index(EDGES, 1), 1);
+        assert_eq!(get_bucket_index(EDGES, 2), 2);
+        assert_eq!(get_bucket_index(EDGES, 5), 2);
+        assert_eq!(get_bucket_index(EDGES, 6), 3);
+        assert_eq!(get_bucket_index(EDGES, 50), 4);
+        assert_eq!(get_bucket_index(EDGES, 51), 5);
+    }
+
+    #[test]
+    fn style_from_default_edges() {
+        assert_eq!(get_style(EDGES, 1.0), EngagementStyle::SoloHunter);
+        assert_eq!(get_style(EDGES, 1.5), EngagementStyle::SmallGang);
+        assert_eq!(get_style(EDGES, 5.0), EngagementStyle::SmallGang);
+        assert_eq!(get_style(EDGES, 6.0), EngagementStyle::Fleet);
+        assert_eq!(get_style(EDGES, 50.0), EngagementStyle::Fleet);
+        assert_eq!(get_style(EDGES, 51.0), EngagementStyle::Blob);
+    }
+
+    #[test]
+    fn style_from_other_edges() {
+        // A first bucket wider than one attacker isn't all solo
+        assert_eq!(get_style(&[3, 10], 1.0), EngagementStyle::SoloHunter);
+        assert_eq!(get_style(&[3, 10], 2.0), EngagementStyle::SmallGang);
+        assert_eq!(get_style(&[3, 10], 4.0), EngagementStyle::Fleet);
+        assert_eq!(get_style(&[3, 10], 11.0), EngagementStyle::Blob);
+        assert_eq!(get_style(&[1], 2.0), EngagementStyle::Blob);
+    }
+
+    #[test]
+    fn summary_of_histogram() {
+        assert!(get_summary(vec![]).is_none());
+        let summary = get_summary(vec![(3, 1), (1, 2)]).unwrap();
+        assert_eq!(summary.median, 1.0);
+        assert!((summary.average - 5.0 / 3.0).abs() < 1e-9);
+        let summary = get_summary(vec![(1, 1), (4, 1)]).unwrap();
+        assert_eq!(summary.median, 2.5);
+    }
+
+    #[test]
+    fn npc_only_losses_have_their_own_bucket() {
+        let profile = get_gang_profile(EDGES, vec![], vec![(0, 2), (3, 1)]);
+        assert_eq!(profile.buckets[0].losses, 2);
+        assert_eq!(profile.buckets[2].losses, 1);
+        assert_eq!(profile.losses.unwrap().median, 3.0);
+        assert!(profile.kills.is_none());
+        assert!(profile.style.is_none());
+    }
+}
@@ -1,108 +1,250 @@
+use crate::config;
+use crate::config::RedisConfig;
+use crate::organization_processing::OrganizationKind;
+use crate::organization_stats_processing::OrganizationStats;
 use crate::stats_processing::CharacterStats;
+use crate::timeseries_processing::{Timeseries, TimeseriesRange, TimeseriesSubject};
 use bb8_redis::bb8::PooledConnection;
 use bb8_redis::{
     bb8,
     RedisConnectionManager,
 };
-use dotenv::dotenv;
-use redis::{ErrorKind};
+use redis::{ConnectionInfo, ErrorKind, IntoConnectionInfo};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use serde_json;
-use std::env;
-const EXPIRE_INTERVAL: usize = 14400;
-
-pub async fn get_redis_pool(url: String) -> bb8_redis::bb8::Pool<RedisConnectionManager> {
-    let manager = RedisConnectionManager::new(url).unwrap();
-    bb8::Pool::builder().build(manager).await.unwrap()
-}
-
-pub async fn init_redis_connection() -> Option<redis::aio::Connection> {
-    dotenv().ok();
-    let redis_password = env::var("REDIS_AUTH_PW");
-    match env::var("REDIS_URL") {
-        Ok(urlval) => {
-            let mut con = get_redis_client(urlval).await;
-            if redis_password.is_ok() {
-                redis::cmd("Auth")
-                    .arg("jager18")
-                    .query_async::<redis::aio::Connection, String>(&mut con)
-                    .await
-                    .expect("Authentication to redis failed");
-            }
-            Some(con)
-        }
-        Err(_) => None,
+use std::time::Duration;
+
+fn get_connection_info(redis_config: &RedisConfig) -> ConnectionInfo {
+    let mut info = redis_config
+        .url
+        .as_str()
+        .into_connection_info()
+        .expect("Invalid redis url");
+    if redis_config.password.is_some() {
+        info.redis.password = redis_config.password.clone();
     }
+    info
 }
 
-async fn get_redis_client(url: String) -> redis::aio::Connection {
-    let client = redis::Client::open(url).expect("Failed to connect to redis server");
+pub async fn get_redis_pool(
+    redis_config: &RedisConfig,
+) -> bb8_redis::bb8::Pool<RedisConnectionManager> {
+    let manager = RedisConnectionManager::new(get_connection_info(redis_config)).unwrap();
+    bb8::Pool::builder()
+        .max_size(redis_config.pool_size)
+        .connection_timeout(Duration::from_millis(redis_config.connection_timeout_ms))
+        .build(manager)
+        .await
+        .unwrap()
+}
+
+pub async fn init_redis_connection() -> redis::aio::Connection {
+    let client = redis::Client::open(get_connection_info(&config::get().redis))
+        .expect("Failed to connect to redis server");
     client
         .get_async_connection()
         .await
         .expect("Failed to get async connection to redis")
 }
 
-pub async fn check_cache_character_stats(
-    mut conn: &mut PooledConnection<'_, RedisConnectionManager>,
-    character_name: &String,
-) -> Option<CharacterStats> {
+/// Stats are cached per window, and names are case-insensitive
+fn get_character_stats_key(character_name: &str, window: &str) -> String {
+    format!(
+        "character_stats:{}:{}",
+        window,
+        character_name.to_lowercase()
+    )
+}
+
+fn get_organization_stats_key(kind: OrganizationKind, name: &str, window: &str) -> String {
+    format!("{}_stats:{}:{}", kind.name(), window, name.to_lowercase())
+}
+
+fn get_timeseries_key(subject: TimeseriesSubject, range: &TimeseriesRange) -> String {
+    format!("timeseries:{}:{}", subject.key(), range.key())
+}
+
+async fn check_cache<T: DeserializeOwned>(
+    conn: &mut PooledConnection<'_, RedisConnectionManager>,
+    key: String,
+    name: &str,
+) -> Option<T> {
     match redis::cmd("GET")
-        .arg(character_name)
-        .query_async::<redis::aio::Connection, String>(&mut conn)
+        .arg(key)
+        .query_async::<redis::aio::Connection, String>(conn)
         .await
     {
         Ok(result_string) => {
-            let stats_result: Result<CharacterStats, serde_json::Error> =
-                serde_json::from_str(&result_string);
+            let stats_result: Result<T, serde_json::Error> = serde_json::from_str(&result_string);
             match stats_result {
                 Ok(stats) => {
-                    info!("Cache hit for {}", character_name);
+                    info!("Cache hit for {}", name);
                     Some(stats)
                 }
                 Err(e) => {
-                    error!(
-                        "Could not deserialize cache result for {}: {:?}",
-                        character_name, e
-                    );
+                    error!("Could not deserialize cache result for {}: {:?}", name, e);
                     None
                 }
             }
         }
         Err(e) => match e.kind() {
             ErrorKind::TypeError => {
-                info!("Cache miss for {}", character_name);
+                info!("Cache miss for {}", name);
                 None
             }
             _ => {
-                error!("Failed to query chache for {}: {:?}", character_name, e);
+                error!("Failed to query chache for {}: {:?}", name, e);
                 None
             }
         },
     }
 }
 
-pub async fn cache_character_stats(
+/// Look up many keys in one round trip. Results line up with `keys`, with
+/// `None` for misses and anything that won't deserialize.
+async fn check_cache_many<T: DeserializeOwned>(
     conn: &mut PooledConnection<'_, RedisConnectionManager>,
-    character_name: &String,
-    info_object: &CharacterStats,
+    keys: Vec<String>,
+) -> Vec<Option<T>> {
+    if keys.is_empty() {
+        return Vec::new();
+    }
+    let key_count = keys.len();
+    match redis::cmd("MGET")
+        .arg(&keys)
+        .query_async::<redis::aio::Connection, Vec<Option<String>>>(conn)
+        .await
+    {
+        Ok(results) => results
+            .into_iter()
+            .zip(keys.iter())
+            .map(|(result, key)| {
+                let result_string = result?;
+                match serde_json::from_str(&result_string) {
+                    Ok(stats) => Some(stats),
+                    Err(e) => {
+                        error!("Could not deserialize cache result for {}: {:?}", key, e);
+                        None
+                    }
+                }
+            })
+            .collect(),
+        Err(e) => {
+            error!("Failed to query cache for {} keys: {:?}", key_count, e);
+            (0..key_count).map(|_| None).collect()
+        }
+    }
+}
+
+async fn cache<T: Serialize>(
+    conn: &mut PooledConnection<'_, RedisConnectionManager>,
+    key: String,
+    name: &str,
+    info_object: &T,
 ) {
     match serde_json::to_string(&info_object) {
         Ok(json_string) => {
-            redis::cmd("SET")
-                .arg(&character_name)
+            // A failed write only costs us a cache miss later
+            match redis::cmd("SET")
+                .arg(key)
                 .arg(json_string)
                 .arg("EX")
-                .arg(EXPIRE_INTERVAL)
+                .arg(config::get().redis.expire_interval_secs)
                 .query_async::<redis::aio::Connection, String>(conn)
                 .await
-                .unwrap();
-            info!("Added stats to cache for {}", character_name);
+            {
+                Ok(_) => info!("Added stats to cache for {}", name),
+                Err(e) => error!("Caching {} failed: {:?}", name, e),
+            }
         }
         Err(e) => {
-            error!(
-                "Caching {} failed, couldn't serialize stats: {:?}",
-                character_name, e
-            );
+            error!("Caching {} failed, couldn't serialize stats: {:?}", name, e);
         }
     }
 }
+
+pub async fn check_cache_character_stats(
+    conn: &mut PooledConnection<'_, RedisConnectionManager>,
+    character_name: &str,
+    window: &str,
+) -> Option<CharacterStats> {
+    check_cache(
+        conn,
+        get_character_stats_key(character_name, window),
+        character_name,
+    )
+    .await
+}
+
+/// Cached stats for each of `character_names`, in the same order
+pub async fn check_cache_many_character_stats(
+    conn: &mut PooledConnection<'_, RedisConnectionManager>,
+    character_names: &[String],
+    window: &str,
+) -> Vec<Option<CharacterStats>> {
+    let keys: Vec<String> = character_names
+        .iter()
+        .map(|character_name| get_character_stats_key(character_name, window))
+        .collect();
+    check_cache_many(conn, keys).await
+}
+
+pub async fn cache_character_stats(
+    conn: &mut PooledConnection<'_, RedisConnectionManager>,
+    character_name: &str,
+    window: &str,
+    info_object: &CharacterStats,
+) {
+    cache(
+        conn,
+        get_character_stats_key(character_name, window),
+        character_name,
+        info_object,
+    )
+    .await
+}
+
+pub async fn check_cache_organization_stats(
+    conn: &mut PooledConnection<'_, RedisConnectionManager>,
+    kind: OrganizationKind,
+    name: &str,
+    window: &str,
+) -> Option<OrganizationStats> {
+    check_cache(conn, get_organization_stats_key(kind, name, window), name).await
+}
+
+pub async fn cache_organization_stats(
+    conn: &mut PooledConnection<'_, RedisConnectionManager>,
+    kind: OrganizationKind,
+    name: &str,
+    window: &str,
+    info_object: &OrganizationStats,
+) {
+    cache(
+        conn,
+        get_organization_stats_key(kind, name, window),
+        name,
+        info_object,
+    )
+    .await
+}
+
+pub async fn check_cache_timeseries(
+    conn: &mut PooledConnection<'_, RedisConnectionManager>,
+    subject: TimeseriesSubject,
+    range: &TimeseriesRange,
+) -> Option<Timeseries> {
+    let key = get_timeseries_key(subject, range);
+    check_cache(conn, key.clone(), &key).await
+}
+
+pub async fn cache_timeseries(
+    conn: &mut PooledConnection<'_, RedisConnectionManager>,
+    subject: TimeseriesSubject,
+    range: &TimeseriesRange,
+    info_object: &Timeseries,
+) {
+    let key = get_timeseries_key(subject, range);
+    cache(conn, key.clone(), &key, info_object).await
+}
@@ -0,0 +1,693 @@
+use crate::entity::prelude::*;
+use crate::entity::*;
+use crate::killmail_processing::ProcessingError;
+use crate::stats_processing;
+use crate::stats_processing::StatsKillmail;
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use schemars::JsonSchema;
+use sea_orm::prelude::*;
+use sea_orm::{DatabaseConnection, DbBackend, DbErr, FromQueryResult, Statement, Value};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+
+const DEFAULT_PAGE_SIZE: u64 = 50;
+const MAX_PAGE_SIZE: u64 = 200;
+
+/// An id with the name we have on file for it, if any
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct NamedId {
+    pub id: u64,
+    pub name: Option<String>,
+}
+
+/// Who someone on a killmail was and what they flew
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct Participant {
+    /// None for NPCs and structures
+    pub character: Option<NamedId>,
+    pub corporation: Option<NamedId>,
+    pub alliance: Option<NamedId>,
+    pub faction_id: Option<u64>,
+    pub ship: Option<NamedId>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct KillmailVictim {
+    #[serde(flatten)]
+    pub participant: Participant,
+    pub damage_taken: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct KillmailAttacker {
+    #[serde(flatten)]
+    pub participant: Participant,
+    pub weapon: Option<NamedId>,
+    pub damage_done: u64,
+    pub final_blow: bool,
+    pub security_status: f32,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct Position {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct KillmailValue {
+    pub ship_value: f64,
+    pub destroyed_value: f64,
+    pub dropped_value: f64,
+    pub total_value: f64,
+    pub valued_at: NaiveDateTime,
+}
+
+/// Everything we know about a single killmail
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct KillmailDetail {
+    pub killmail_id: u64,
+    pub killmail_time: NaiveDateTime,
+    pub solar_system: NamedId,
+    pub awox: bool,
+    pub position: Option<Position>,
+    /// None until the valuation job has priced the killmail
+    pub value: Option<KillmailValue>,
+    pub victim: KillmailVictim,
+    /// Ordered by damage done, highest first
+    pub attackers: Vec<KillmailAttacker>,
+}
+
+/// The short form of a killmail used in listings
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct KillmailSummary {
+    pub killmail_id: u64,
+    pub killmail_time: NaiveDateTime,
+    pub solar_system: NamedId,
+    pub awox: bool,
+    pub victim: Participant,
+    pub final_blow: Option<Participant>,
+    /// Attackers flown by players, NPCs aren't counted
+    pub attacker_count: usize,
+    pub solo: bool,
+    pub total_value: Option<f64>,
+}
+
+/// Which of a pilot's killmails to list
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum KillmailSide {
+    Kills,
+    Losses,
+}
+
+impl KillmailSide {
+    fn parse(side: &str) -> Option<KillmailSide> {
+        match side {
+            "kills" => Some(KillmailSide::Kills),
+            "losses" => Some(KillmailSide::Losses),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct CharacterKillmail {
+    /// Whether the pilot got the kill or lost the ship
+    pub side: KillmailSide,
+    #[serde(flatten)]
+    pub killmail: KillmailSummary,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct CharacterKillmailPage {
+    pub character_id: u64,
+    /// Newest first
+    pub killmails: Vec<CharacterKillmail>,
+    /// Pass as `cursor` to get the next page, None on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// Where a page starts: the killmail after the last one on the previous page
+#[derive(Debug, Clone, Copy)]
+pub struct KillmailCursor {
+    pub killmail_time: NaiveDateTime,
+    pub killmail_id: u64,
+}
+
+impl KillmailCursor {
+    pub fn parse(cursor: &str) -> Option<KillmailCursor> {
+        let (timestamp, killmail_id) = cursor.split_once('_')?;
+        Some(KillmailCursor {
+            killmail_time: NaiveDateTime::from_timestamp_opt(timestamp.parse().ok()?, 0)?,
+            killmail_id: killmail_id.parse().ok()?,
+        })
+    }
+
+    pub fn encode(&self) -> String {
+        format!("{}_{}", self.killmail_time.timestamp(), self.killmail_id)
+    }
+}
+
+/// Filters for a pilot's killmail listing. Leaving `side` out lists both
+/// kills and losses.
+#[derive(Debug, Clone)]
+pub struct CharacterKillmailFilter {
+    pub side: Option<KillmailSide>,
+    pub from: Option<NaiveDate>,
+    /// Inclusive
+    pub to: Option<NaiveDate>,
+    /// The ship the pilot flew, not the victim's ship on kills
+    pub ship_type_id: Option<u64>,
+    /// Only killmails with a single player attacker
+    pub solo: bool,
+    pub cursor: Option<KillmailCursor>,
+    pub limit: u64,
+}
+
+impl CharacterKillmailFilter {
+    /// Build a filter from query parameters
+    pub fn parse(
+        side: Option<&str>,
+        from: Option<&str>,
+        to: Option<&str>,
+        ship_type_id: Option<u64>,
+        solo: Option<bool>,
+        cursor: Option<&str>,
+        limit: Option<u64>,
+    ) -> Result<CharacterKillmailFilter, String> {
+        let parse_date = |date: &str| {
+            NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map_err(|_| format!("Couldn't parse date {}, expected YYYY-MM-DD", date))
+        };
+        let side = match side {
+            Some(side) => Some(
+                KillmailSide::parse(side)
+                    .ok_or_else(|| format!("Unknown side {}, expected kills or losses", side))?,
+            ),
+            None => None,
+        };
+        let from = from.map(parse_date).transpose()?;
+        let to = to.map(parse_date).transpose()?;
+        if let (Some(from), Some(to)) = (from, to) {
+            if from > to {
+                return Err(format!("from ({}) is after to ({})", from, to));
+            }
+        }
+        let cursor = match cursor {
+            Some(cursor) => Some(
+                KillmailCursor::parse(cursor)
+                    .ok_or_else(|| format!("Invalid cursor {}", cursor))?,
+            ),
+            None => None,
+        };
+        Ok(CharacterKillmailFilter {
+            side,
+            from,
+            to,
+            ship_type_id,
+            solo: solo.unwrap_or(false),
+            cursor,
+            limit: parse_page_limit(limit)?,
+        })
+    }
+}
+
+/// The number of killmails to put on a page, 50 unless asked otherwise
+pub fn parse_page_limit(limit: Option<u64>) -> Result<u64, String> {
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(format!("limit must be between 1 and {}", MAX_PAGE_SIZE));
+    }
+    Ok(limit)
+}
+
+#[derive(Default)]
+struct NameIds {
+    characters: HashSet<u64>,
+    corporations: HashSet<u64>,
+    alliances: HashSet<u64>,
+    types: HashSet<u64>,
+    systems: HashSet<u64>,
+}
+
+impl NameIds {
+    fn add_participant(
+        &mut self,
+        character_id: Option<u64>,
+        corporation_id: Option<u64>,
+        alliance_id: Option<u64>,
+        ship_type_id: Option<u64>,
+    ) {
+        self.characters.extend(character_id);
+        self.corporations.extend(corporation_id);
+        self.alliances.extend(alliance_id);
+        self.types.extend(ship_type_id);
+    }
+
+    fn add_victim(&mut self, victim: &victims::Model) {
+        self.add_participant(
+            victim.character_id,
+            victim.corporation_id,
+            victim.alliance_id,
+            Some(victim.ship_type_id),
+        );
+    }
+
+    fn add_attacker(&mut self, attacker: &attackers::Model) {
+        self.add_participant(
+            attacker.character_id,
+            attacker.corporation_id,
+            attacker.alliance_id,
+            attacker.ship_type_id,
+        );
+        self.types.extend(attacker.weapon_type_id);
+    }
+}
+
+#[derive(Default)]
+struct Names {
+    characters: HashMap<u64, String>,
+    corporations: HashMap<u64, String>,
+    alliances: HashMap<u64, String>,
+    types: HashMap<u64, String>,
+    systems: HashMap<u64, String>,
+}
+
+fn named(names: &HashMap<u64, String>, id: Option<u64>) -> Option<NamedId> {
+    id.map(|id| NamedId {
+        id,
+        name: names.get(&id).cloned(),
+    })
+}
+
+impl Names {
+    /// One query per kind of name, only for kinds that have ids
+    async fn load(db: &DatabaseConnection, ids: NameIds) -> Result<Names, DbErr> {
+        let mut names = Names::default();
+        if !ids.characters.is_empty() {
+            names.characters = CharacterPublicInfo::find()
+                .filter(character_public_info::Column::CharacterId.is_in(ids.characters))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|character| (character.character_id, character.character_name))
+                .collect();
+        }
+        if !ids.corporations.is_empty() {
+            names.corporations = Corporations::find()
+                .filter(corporations::Column::CorporationId.is_in(ids.corporations))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|corporation| (corporation.corporation_id, corporation.name))
+                .collect();
+        }
+        if !ids.alliances.is_empty() {
+            names.alliances = Alliances::find()
+                .filter(alliances::Column::AllianceId.is_in(ids.alliances))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|alliance| (alliance.alliance_id, alliance.name))
+                .collect();
+        }
+        if !ids.types.is_empty() {
+            names.types = EsiTypes::find()
+                .filter(esi_types::Column::TypeId.is_in(ids.types))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|esi_type| (esi_type.type_id, esi_type.type_name))
+                .collect();
+        }
+        if !ids.systems.is_empty() {
+            names.systems = SolarSystems::find()
+                .filter(solar_systems::Column::SystemId.is_in(ids.systems))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|system| (system.system_id, system.name))
+                .collect();
+        }
+        Ok(names)
+    }
+
+    fn system(&self, system_id: u64) -> NamedId {
+        NamedId {
+            id: system_id,
+            name: self.systems.get(&system_id).cloned(),
+        }
+    }
+
+    fn participant(
+        &self,
+        character_id: Option<u64>,
+        corporation_id: Option<u64>,
+        alliance_id: Option<u64>,
+        faction_id: Option<u64>,
+        ship_type_id: Option<u64>,
+    ) -> Participant {
+        Participant {
+            character: named(&self.characters, character_id),
+            corporation: named(&self.corporations, corporation_id),
+            alliance: named(&self.alliances, alliance_id),
+            faction_id,
+            ship: named(&self.types, ship_type_id),
+        }
+    }
+
+    fn victim(&self, victim: &victims::Model) -> Participant {
+        self.participant(
+            victim.character_id,
+            victim.corporation_id,
+            victim.alliance_id,
+            victim.faction_id,
+            Some(victim.ship_type_id),
+        )
+    }
+
+    fn attacker(&self, attacker: &attackers::Model) -> Participant {
+        self.participant(
+            attacker.character_id,
+            attacker.corporation_id,
+            attacker.alliance_id,
+            attacker.faction_id,
+            attacker.ship_type_id,
+        )
+    }
+}
+
+async fn get_killmail_values(
+    db: &DatabaseConnection,
+    killmail_ids: Vec<u64>,
+) -> Result<HashMap<u64, killmail_values::Model>, DbErr> {
+    Ok(KillmailValues::find()
+        .filter(killmail_values::Column::KillmailId.is_in(killmail_ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|value| (value.killmail_id, value))
+        .collect())
+}
+
+/// A killmail with its victim, attackers, position and value, with names
+/// resolved for everything we have names for
+pub async fn get_killmail_detail(
+    db: &DatabaseConnection,
+    killmail_id: u64,
+) -> Result<Option<KillmailDetail>, DbErr> {
+    let mut killmail = match stats_processing::get_kills_from_list(db, vec![killmail_id])
+        .await?
+        .pop()
+    {
+        Some(killmail) => killmail,
+        None => return Ok(None),
+    };
+    let mut ids = NameIds::default();
+    ids.systems.insert(killmail.solar_system_id);
+    ids.add_victim(&killmail.victim);
+    for attacker in &killmail.attackers {
+        ids.add_attacker(attacker);
+    }
+    let names = Names::load(db, ids).await?;
+    let value = get_killmail_values(db, vec![killmail_id])
+        .await?
+        .remove(&killmail_id);
+    killmail
+        .attackers
+        .sort_by_key(|attacker| Reverse(attacker.damage_done));
+    Ok(Some(KillmailDetail {
+        killmail_id: killmail.killmail_id,
+        killmail_time: killmail.killmail_time,
+        solar_system: names.system(killmail.solar_system_id),
+        awox: killmail.awox,
+        position: killmail.position.map(|position| Position {
+            x: position.x,
+            y: position.y,
+            z: position.z,
+        }),
+        value: value.map(|value| KillmailValue {
+            ship_value: value.ship_value,
+            destroyed_value: value.destroyed_value,
+            dropped_value: value.dropped_value,
+            total_value: value.total_value,
+            valued_at: value.valued_at,
+        }),
+        victim: KillmailVictim {
+            participant: names.victim(&killmail.victim),
+            damage_taken: killmail.victim.damage_taken,
+        },
+        attackers: killmail
+            .attackers
+            .iter()
+            .map(|attacker| KillmailAttacker {
+                participant: names.attacker(attacker),
+                weapon: named(&names.types, attacker.weapon_type_id),
+                damage_done: attacker.damage_done,
+                final_blow: attacker.final_blow,
+                security_status: attacker.security_status,
+            })
+            .collect(),
+    }))
+}
+
+/// Summaries for `killmail_ids`, in the order given. Only the victim and the
+/// final blow get names, listings don't show the rest of the attackers.
+pub async fn get_killmail_summaries(
+    db: &DatabaseConnection,
+    killmail_ids: Vec<u64>,
+) -> Result<Vec<KillmailSummary>, DbErr> {
+    let mut killmails: HashMap<u64, StatsKillmail> =
+        stats_processing::get_kills_from_list(db, killmail_ids.clone())
+            .await?
+            .into_iter()
+            .map(|killmail| (killmail.killmail_id, killmail))
+            .collect();
+    let mut ids = NameIds::default();
+    for killmail in killmails.values() {
+        ids.systems.insert(killmail.solar_system_id);
+        ids.add_victim(&killmail.victim);
+        if let Some(final_blow) = killmail.attackers.iter().find(|a| a.final_blow) {
+            ids.add_attacker(final_blow);
+        }
+    }
+    let names = Names::load(db, ids).await?;
+    let values = get_killmail_values(db, killmail_ids.clone()).await?;
+    Ok(killmail_ids
+        .into_iter()
+        .filter_map(|killmail_id| killmails.remove(&killmail_id))
+        .map(|killmail| {
+            let attacker_count = stats_processing::get_attacker_player_count(&killmail.attackers);
+            KillmailSummary {
+                killmail_id: killmail.killmail_id,
+                killmail_time: killmail.killmail_time,
+                solar_system: names.system(killmail.solar_system_id),
+                awox: killmail.awox,
+                victim: names.victim(&killmail.victim),
+                final_blow: killmail
+                    .attackers
+                    .iter()
+                    .find(|attacker| attacker.final_blow)
+                    .map(|attacker| names.attacker(attacker)),
+                attacker_count,
+                solo: attacker_count == 1,
+                total_value: values
+                    .get(&killmail.killmail_id)
+                    .map(|value| value.total_value),
+            }
+        })
+        .collect())
+}
+
+/// Pages are fetched with one row more than `limit`. Drops that row and
+/// returns the cursor for the next page if it was there.
+pub fn take_page(keys: &mut Vec<KillmailCursor>, limit: u64) -> Option<String> {
+    if keys.len() as u64 <= limit {
+        return None;
+    }
+    keys.truncate(limit as usize);
+    keys.last().map(KillmailCursor::encode)
+}
+
+#[derive(Debug, FromQueryResult)]
+struct KillmailKey {
+    killmail_id: u64,
+    killmail_time: NaiveDateTime,
+}
+
+/// One page of a pilot's killmails, newest first
+pub async fn get_character_killmails_page(
+    db: &DatabaseConnection,
+    character_id: u64,
+    filter: &CharacterKillmailFilter,
+) -> Result<CharacterKillmailPage, DbErr> {
+    let mut values: Vec<Value> = Vec::new();
+    let mut sides: Vec<String> = Vec::new();
+    for (side, table) in [
+        (KillmailSide::Kills, "attackers"),
+        (KillmailSide::Losses, "victims"),
+    ] {
+        if filter.side.is_none_or(|wanted| wanted == side) {
+            let mut clause = format!(
+                "k.killmail_id IN (SELECT p.killmail_id FROM {} p WHERE p.character_id = ?",
+                table
+            );
+            values.push(character_id.into());
+            if let Some(ship_type_id) = filter.ship_type_id {
+                clause.push_str(" AND p.ship_type_id = ?");
+                values.push(ship_type_id.into());
+            }
+            clause.push(')');
+            sides.push(clause);
+        }
+    }
+    let mut sql = format!(
+        "SELECT k.killmail_id, k.killmail_time FROM killmails k WHERE ({})",
+        sides.join(" OR ")
+    );
+    if let Some(from) = filter.from {
+        sql.push_str(" AND k.killmail_time >= ?");
+        values.push(from.and_hms(0, 0, 0).into());
+    }
+    if let Some(to) = filter.to {
+        sql.push_str(" AND k.killmail_time < ?");
+        values.push((to + Duration::days(1)).and_hms(0, 0, 0).into());
+    }
+    if filter.solo {
+        sql.push_str(
+            r#" AND (SELECT COUNT(s.character_id) FROM attackers s
+                WHERE s.killmail_id = k.killmail_id) = 1"#,
+        );
+    }
+    if let Some(cursor) = filter.cursor {
+        sql.push_str(" AND (k.killmail_time < ? OR (k.killmail_time = ? AND k.killmail_id < ?))");
+        values.push(cursor.killmail_time.into());
+        values.push(cursor.killmail_time.into());
+        values.push(cursor.killmail_id.into());
+    }
+    // One extra row tells us whether there's another page
+    sql.push_str(" ORDER BY k.killmail_time DESC, k.killmail_id DESC LIMIT ?");
+    values.push((filter.limit + 1).into());
+    let mut keys: Vec<KillmailCursor> = KillmailKey::find_by_statement(
+        Statement::from_sql_and_values(DbBackend::MySql, &sql, values),
+    )
+    .all(db)
+    .await?
+    .into_iter()
+    .map(|key| KillmailCursor {
+        killmail_time: key.killmail_time,
+        killmail_id: key.killmail_id,
+    })
+    .collect();
+    let next_cursor = take_page(&mut keys, filter.limit);
+    let summaries =
+        get_killmail_summaries(db, keys.iter().map(|key| key.killmail_id).collect()).await?;
+    Ok(CharacterKillmailPage {
+        character_id,
+        killmails: summaries
+            .into_iter()
+            .map(|killmail| CharacterKillmail {
+                side: if killmail.victim.character.as_ref().map(|c| c.id) == Some(character_id) {
+                    KillmailSide::Losses
+                } else {
+                    KillmailSide::Kills
+                },
+                killmail,
+            })
+            .collect(),
+        next_cursor,
+    })
+}
+
+/// Like `get_character_killmails_page`, by character name. None if the pilot
+/// doesn't exist.
+pub async fn get_character_killmails(
+    db: &DatabaseConnection,
+    name: String,
+    filter: &CharacterKillmailFilter,
+) -> Result<Option<CharacterKillmailPage>, ProcessingError> {
+    match stats_processing::get_or_update_character_public_info(db, name).await? {
+        Some(char_info) => Ok(Some(
+            get_character_killmails_page(db, char_info.character_id, filter).await?,
+        )),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cursor(timestamp: i64, killmail_id: u64) -> KillmailCursor {
+        KillmailCursor {
+            killmail_time: NaiveDateTime::from_timestamp(timestamp, 0),
+            killmail_id,
+        }
+    }
+
+    #[test]
+    fn cursors_round_trip() {
+        let encoded = cursor(1646000000, 98765432).encode();
+        assert_eq!(encoded, "1646000000_98765432");
+        let parsed = KillmailCursor::parse(&encoded).unwrap();
+        assert_eq!(parsed.killmail_time.timestamp(), 1646000000);
+        assert_eq!(parsed.killmail_id, 98765432);
+    }
+
+    #[test]
+    fn rejects_malformed_cursors() {
+        for malformed in [
+            "",
+            "_",
+            "1646000000",
+            "1646000000_",
+            "_98765432",
+            "1646000000_98765432_1",
+            "1646000000_-1",
+            "yesterday_98765432",
+            "99999999999999999999_1",
+        ] {
+            assert!(
+                KillmailCursor::parse(malformed).is_none(),
+                "parsed {:?}",
+                malformed
+            );
+        }
+    }
+
+    #[test]
+    fn page_limit_is_bounded() {
+        assert_eq!(parse_page_limit(None), Ok(DEFAULT_PAGE_SIZE));
+        assert_eq!(parse_page_limit(Some(1)), Ok(1));
+        assert_eq!(parse_page_limit(Some(MAX_PAGE_SIZE)), Ok(MAX_PAGE_SIZE));
+        assert!(parse_page_limit(Some(0)).is_err());
+        assert!(parse_page_limit(Some(MAX_PAGE_SIZE + 1)).is_err());
+    }
+
+    #[test]
+    fn take_page_points_at_the_last_kept_killmail() {
+        let mut keys = vec![cursor(300, 3), cursor(200, 2), cursor(100, 1)];
+        assert_eq!(take_page(&mut keys, 3), None);
+        assert_eq!(keys.len(), 3);
+        assert_eq!(take_page(&mut keys, 2), Some("200_2".to_string()));
+        assert_eq!(keys.len(), 2);
+    }
+
+    #[test]
+    fn filter_rejects_bad_parameters() {
+        let parse = |side, from, to, cursor| {
+            CharacterKillmailFilter::parse(side, from, to, None, None, cursor, None)
+        };
+        assert!(parse(Some("kills"), Some("2022-03-01"), Some("2022-03-01"), None).is_ok());
+        assert!(parse(Some("wins"), None, None, None).is_err());
+        assert!(parse(None, Some("2022-03-02"), Some("2022-03-01"), None).is_err());
+        assert!(parse(None, Some("03/01/2022"), None, None).is_err());
+        assert!(parse(None, None, None, Some("not-a-cursor")).is_err());
+        let filter = parse(Some("losses"), None, None, Some("1646000000_5")).unwrap();
+        assert_eq!(filter.cursor.unwrap().killmail_id, 5);
+        assert!(!filter.solo);
+    }
+}
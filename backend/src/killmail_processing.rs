@@ -1,3 +1,4 @@
+use crate::config;
 use crate::database;
 use crate::database::JagerDatabaseError;
 use crate::entity::prelude::*;
@@ -5,21 +6,26 @@ use crate::entity::*;
 use crate::esi;
 use crate::esi::EsiError;
 use crate::organization_processing;
-use database::CreateOrExist;
+use crate::summary_processing;
+use crate::valuation_processing;
 use datamodels::esi_models::{
-    ESIAttacker, ESIKillPosition, ESIKillmail, ESIKillmailRequest, ESIVictim,
+    ESIAttacker, ESIItem, ESIKillPosition, ESIKillmail, ESIKillmailRequest, ESIVictim,
 };
 use futures::{stream, StreamExt};
 use pbr::ProgressBar;
 use sea_orm::prelude::*;
 use sea_orm::DatabaseConnection;
+use sea_orm::DatabaseTransaction;
 use sea_orm::DbErr;
+use sea_orm::TransactionTrait;
 
 #[derive(Debug)]
 pub enum ProcessingError {
     ESIError(EsiError),
     DBError(DbErr),
     JagerDatabaseError(JagerDatabaseError),
+    AmbiguousCharacterName(Vec<u64>),
+    AmbiguousOrganizationName(Vec<u64>),
 }
 
 impl From<EsiError> for ProcessingError {
@@ -40,10 +46,11 @@ impl From<JagerDatabaseError> for ProcessingError {
     }
 }
 
-pub async fn process_victim(
+/// Make sure the victim's character, corporation and alliance exist before
+/// the victim row referencing them is written
+pub async fn store_victim_references(
     db: &DatabaseConnection,
-    victim: ESIVictim,
-    killmail_id: u64,
+    victim: &ESIVictim,
 ) -> Result<(), ProcessingError> {
     if let Some(alliance_id) = victim.alliance_id {
         organization_processing::store_alliance_if_not_present(db, alliance_id).await?;
@@ -54,51 +61,83 @@ pub async fn process_victim(
     if let Some(char_id) = victim.character_id {
         organization_processing::store_pubchar_info_if_not_present(db, char_id).await?;
     }
-    let victim_insertable = victims::ActiveModel::from_esi(victim, killmail_id);
-    database::insert_single(db, victim_insertable).await?;
     Ok(())
 }
 
-pub async fn process_attackers(
+/// Make sure every attacker's character, corporation and alliance exist
+/// before the attacker rows referencing them are written
+pub async fn store_attacker_references(
     db: &DatabaseConnection,
-    attackers: Vec<ESIAttacker>,
-    killmail_id: u64,
+    attackers: &[ESIAttacker],
 ) -> Result<(), ProcessingError> {
-    // Create characters
     let character_ids: Vec<u64> = attackers
-        .clone()
-        .into_iter()
-        .filter_map(|option| option.character_id)
+        .iter()
+        .filter_map(|attacker| attacker.character_id)
         .collect();
     let corporation_ids: Vec<u64> = attackers
-        .clone()
-        .into_iter()
-        .filter_map(|character| character.corporation_id)
+        .iter()
+        .filter_map(|attacker| attacker.corporation_id)
         .collect();
     let alliance_ids: Vec<u64> = attackers
-        .clone()
-        .into_iter()
-        .filter_map(|character| character.alliance_id)
+        .iter()
+        .filter_map(|attacker| attacker.alliance_id)
         .collect();
     organization_processing::store_alliances_if_not_present(db, alliance_ids).await?;
     organization_processing::store_corporations_if_not_present(db, corporation_ids).await?;
     organization_processing::store_pubchars_info_if_not_present(db, character_ids).await?;
+    Ok(())
+}
+
+pub async fn process_victim(
+    txn: &DatabaseTransaction,
+    victim: ESIVictim,
+    killmail_id: u64,
+) -> Result<(), ProcessingError> {
+    let victim_insertable = victims::ActiveModel::from_esi(victim, killmail_id);
+    victim_insertable.insert(txn).await?;
+    Ok(())
+}
+
+pub async fn process_attackers(
+    txn: &DatabaseTransaction,
+    attackers: Vec<ESIAttacker>,
+    killmail_id: u64,
+) -> Result<(), ProcessingError> {
     let attacker_insertables: Vec<attackers::ActiveModel> = attackers
         .into_iter()
         .map(|attacker| attackers::ActiveModel::from_esi(attacker, killmail_id))
         .collect();
-    database::insert_multiple_attackers(db, attacker_insertables).await?;
+    if !attacker_insertables.is_empty() {
+        Attackers::insert_many(attacker_insertables)
+            .exec(txn)
+            .await?;
+    }
+    Ok(())
+}
+
+pub async fn process_items(
+    txn: &DatabaseTransaction,
+    items: Vec<ESIItem>,
+    killmail_id: u64,
+) -> Result<(), ProcessingError> {
+    let item_insertables: Vec<victim_items::ActiveModel> = items
+        .into_iter()
+        .flat_map(|item| victim_items::ActiveModel::from_esi(item, killmail_id))
+        .collect();
+    if !item_insertables.is_empty() {
+        VictimItems::insert_many(item_insertables).exec(txn).await?;
+    }
     Ok(())
 }
 
 pub async fn process_position(
-    db: &DatabaseConnection,
+    txn: &DatabaseTransaction,
     position: ESIKillPosition,
     killmail_id: u64,
 ) -> Result<(), ProcessingError> {
     let position_insertable: killmail_positions::ActiveModel =
         killmail_positions::ActiveModel::from_esi(position, killmail_id);
-    database::insert_single(db, position_insertable).await?;
+    position_insertable.insert(txn).await?;
     Ok(())
 }
 
@@ -107,31 +146,49 @@ pub async fn process_killmail(
     killmail: ESIKillmail,
 ) -> Result<(), ProcessingError> {
     let killmail_id = killmail.killmail_id;
+    if Killmails::find_by_id(killmail_id).one(db).await?.is_some() {
+        info!(
+            "Killmail {} exists in db already, skipping processing",
+            killmail_id
+        );
+        return Ok(());
+    }
+    // fetch pubchar info for everyone involved and store their corporations
+    // and alliances, the killmail's rows reference them
+    store_victim_references(db, &killmail.victim).await?;
+    store_attacker_references(db, &killmail.attackers).await?;
     let killmail_attackers = killmail.clone().attackers;
     let killmail_victim = killmail.clone().victim;
-    let killmail_insertable = killmails::ActiveModel::from(killmail);
+    let killmail_insertable = killmails::ActiveModel::from(killmail.clone());
     let killmail_position = killmail_victim.clone().position;
-    // If killmail doesn't exist, create it. If it does exist, exit
-    match database::insert_killmail_if_not_present(db, killmail_insertable).await? {
-        CreateOrExist::Exists => {
+    let killmail_items = killmail_victim.clone().items;
+    // The killmail and everything hanging off it are written in one
+    // transaction together with everyone's daily stats, so a failure can't
+    // leave it stored but incomplete or counted twice. If another ingest
+    // stored it first, exit.
+    let txn = db.begin().await?;
+    if let Err(err) = killmail_insertable.insert(&txn).await {
+        if database::is_duplicate_err(&err) {
             info!(
                 "Killmail {} exists in db already, skipping processing",
                 killmail_id
             );
-            Ok(())
-        }
-        CreateOrExist::Created => {
-            // fetch victim pubchar info and insert victim
-            process_victim(db, killmail_victim, killmail_id).await?;
-            // fetch attackers pubchar info and insert attackers
-            process_attackers(db, killmail_attackers, killmail_id).await?;
-            // if there is a position, insert the position
-            if let Some(position) = killmail_position {
-                process_position(db, position, killmail_id).await?;
-            }
-            Ok(())
+            return Ok(());
         }
+        return Err(err.into());
     }
+    process_victim(&txn, killmail_victim, killmail_id).await?;
+    process_items(&txn, killmail_items, killmail_id).await?;
+    process_attackers(&txn, killmail_attackers, killmail_id).await?;
+    // if there is a position, insert the position
+    if let Some(position) = killmail_position {
+        process_position(&txn, position, killmail_id).await?;
+    }
+    summary_processing::record_killmail(&txn, &killmail).await?;
+    txn.commit().await?;
+    // value it at the latest prices we have
+    valuation_processing::value_killmails(db, &[killmail_id]).await?;
+    Ok(())
 }
 
 pub async fn process_esi_killmail(
@@ -161,7 +218,7 @@ pub async fn process_killmails(
 ) -> Result<(), ProcessingError> {
     let mut bodies = stream::iter(requests)
         .map(|req| async move { process_esi_killmail(db, req).await })
-        .buffer_unordered(20);
+        .buffer_unordered(config::get().concurrency.killmails);
     while let Some(result) = bodies.next().await {
         match result {
             Ok(_) => {
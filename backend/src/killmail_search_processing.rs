@@ -0,0 +1,282 @@
+use crate::entity::prelude::*;
+use crate::entity::*;
+use crate::killmail_detail_processing;
+use crate::killmail_detail_processing::{KillmailCursor, KillmailSummary};
+use chrono::NaiveDateTime;
+use schemars::JsonSchema;
+use sea_orm::prelude::*;
+use sea_orm::sea_query::{Expr, Query, SelectStatement, SimpleExpr};
+use sea_orm::{Condition, DatabaseConnection, DbErr, PaginatorTrait, QueryOrder, QuerySelect};
+use serde::{Deserialize, Serialize};
+
+/// Conditions on a single victim or attacker. For attackers every condition
+/// has to hold for the same attacker.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+pub struct ParticipantFilter {
+    pub character_id: Option<u64>,
+    pub corporation_id: Option<u64>,
+    pub alliance_id: Option<u64>,
+    pub faction_id: Option<u64>,
+    pub ship_type_id: Option<u64>,
+    pub ship_group_id: Option<u64>,
+}
+
+impl ParticipantFilter {
+    fn is_empty(&self) -> bool {
+        self.character_id.is_none()
+            && self.corporation_id.is_none()
+            && self.alliance_id.is_none()
+            && self.faction_id.is_none()
+            && self.ship_type_id.is_none()
+            && self.ship_group_id.is_none()
+    }
+}
+
+/// A killmail search. Every filter that's set has to match, and at least a
+/// participant, a location or `from` has to be set.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+pub struct KillmailSearch {
+    pub victim: Option<ParticipantFilter>,
+    pub attacker: Option<ParticipantFilter>,
+    pub solar_system_id: Option<u64>,
+    pub region_id: Option<u64>,
+    pub from: Option<NaiveDateTime>,
+    /// Exclusive
+    pub to: Option<NaiveDateTime>,
+    /// Counts attackers flown by players
+    pub min_attackers: Option<u64>,
+    /// Only killmails with a single player attacker, or with any other number
+    /// of them if false
+    pub solo: Option<bool>,
+    /// `next_cursor` from the previous page
+    pub cursor: Option<String>,
+    pub limit: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct KillmailSearchResults {
+    /// Killmails matching the search across all pages, only counted for the
+    /// first page
+    pub total: Option<usize>,
+    /// Newest first
+    pub killmails: Vec<KillmailSummary>,
+    pub next_cursor: Option<String>,
+}
+
+/// The columns victims and attackers have in common
+struct ParticipantColumns<C> {
+    killmail_id: C,
+    character_id: C,
+    corporation_id: C,
+    alliance_id: C,
+    faction_id: C,
+    ship_type_id: C,
+}
+
+/// The ids of killmails with a participant in `entity` matching `filter`, or
+/// None if the filter is empty
+fn participant_subquery<E: EntityTrait>(
+    entity: E,
+    columns: ParticipantColumns<E::Column>,
+    filter: &ParticipantFilter,
+) -> Option<SelectStatement> {
+    let mut condition = Condition::all();
+    let mut empty = true;
+    for (column, id) in [
+        (columns.character_id, filter.character_id),
+        (columns.corporation_id, filter.corporation_id),
+        (columns.alliance_id, filter.alliance_id),
+        (columns.faction_id, filter.faction_id),
+        (columns.ship_type_id, filter.ship_type_id),
+    ] {
+        if let Some(id) = id {
+            condition = condition.add(column.eq(id));
+            empty = false;
+        }
+    }
+    if let Some(group_id) = filter.ship_group_id {
+        condition = condition.add(
+            columns.ship_type_id.in_subquery(
+                Query::select()
+                    .column(esi_types::Column::TypeId)
+                    .from(EsiTypes)
+                    .and_where(esi_types::Column::GroupId.eq(group_id))
+                    .to_owned(),
+            ),
+        );
+        empty = false;
+    }
+    if empty {
+        return None;
+    }
+    Some(
+        Query::select()
+            .column(columns.killmail_id)
+            .from(entity)
+            .cond_where(condition)
+            .to_owned(),
+    )
+}
+
+/// Number of player attackers on a killmail, compared with `operator`
+fn attacker_count(operator: &str, count: u64) -> SimpleExpr {
+    Expr::cust_with_values(
+        &format!(
+            "(SELECT COUNT(a.character_id) FROM attackers a
+                WHERE a.killmail_id = killmails.killmail_id) {} ?",
+            operator
+        ),
+        vec![count],
+    )
+}
+
+/// Turn a search into a condition on killmails, leaving out pagination
+fn build_condition(search: &KillmailSearch) -> Condition {
+    let mut condition = Condition::all();
+    if let Some(victim) = &search.victim {
+        let columns = ParticipantColumns {
+            killmail_id: victims::Column::KillmailId,
+            character_id: victims::Column::CharacterId,
+            corporation_id: victims::Column::CorporationId,
+            alliance_id: victims::Column::AllianceId,
+            faction_id: victims::Column::FactionId,
+            ship_type_id: victims::Column::ShipTypeId,
+        };
+        if let Some(subquery) = participant_subquery(Victims, columns, victim) {
+            condition = condition.add(killmails::Column::KillmailId.in_subquery(subquery));
+        }
+    }
+    if let Some(attacker) = &search.attacker {
+        let columns = ParticipantColumns {
+            killmail_id: attackers::Column::KillmailId,
+            character_id: attackers::Column::CharacterId,
+            corporation_id: attackers::Column::CorporationId,
+            alliance_id: attackers::Column::AllianceId,
+            faction_id: attackers::Column::FactionId,
+            ship_type_id: attackers::Column::ShipTypeId,
+        };
+        if let Some(subquery) = participant_subquery(Attackers, columns, attacker) {
+            condition = condition.add(killmails::Column::KillmailId.in_subquery(subquery));
+        }
+    }
+    if let Some(solar_system_id) = search.solar_system_id {
+        condition = condition.add(killmails::Column::SolarSystemId.eq(solar_system_id));
+    }
+    if let Some(region_id) = search.region_id {
+        condition = condition.add(
+            killmails::Column::SolarSystemId.in_subquery(
+                Query::select()
+                    .column(solar_systems::Column::SystemId)
+                    .from(SolarSystems)
+                    .and_where(
+                        solar_systems::Column::ConstellationId.in_subquery(
+                            Query::select()
+                                .column(constellations::Column::ConstellationId)
+                                .from(Constellations)
+                                .and_where(constellations::Column::RegionId.eq(region_id))
+                                .to_owned(),
+                        ),
+                    )
+                    .to_owned(),
+            ),
+        );
+    }
+    if let Some(from) = search.from {
+        condition = condition.add(killmails::Column::KillmailTime.gte(from));
+    }
+    if let Some(to) = search.to {
+        condition = condition.add(killmails::Column::KillmailTime.lt(to));
+    }
+    if let Some(min_attackers) = search.min_attackers {
+        condition = condition.add(attacker_count(">=", min_attackers));
+    }
+    match search.solo {
+        Some(true) => condition = condition.add(attacker_count("=", 1)),
+        Some(false) => condition = condition.add(attacker_count("<>", 1)),
+        None => {}
+    }
+    condition
+}
+
+/// Killmails after `cursor` in newest first order
+fn after_cursor(cursor: KillmailCursor) -> Condition {
+    Condition::any()
+        .add(killmails::Column::KillmailTime.lt(cursor.killmail_time))
+        .add(
+            Condition::all()
+                .add(killmails::Column::KillmailTime.eq(cursor.killmail_time))
+                .add(killmails::Column::KillmailId.lt(cursor.killmail_id)),
+        )
+}
+
+/// Whether a search narrows killmails down enough to run. The other filters
+/// match too many killmails to be searched on their own.
+fn is_selective(search: &KillmailSearch) -> bool {
+    let has_participant = [&search.victim, &search.attacker]
+        .iter()
+        .any(|filter| filter.as_ref().is_some_and(|filter| !filter.is_empty()));
+    has_participant
+        || search.solar_system_id.is_some()
+        || search.region_id.is_some()
+        || search.from.is_some()
+}
+
+/// Check a search before running it, returns the page size
+pub fn validate_search(search: &KillmailSearch) -> Result<u64, String> {
+    if !is_selective(search) {
+        return Err(
+            "Searches need a victim or attacker filter, a solar system or region, or from"
+                .to_string(),
+        );
+    }
+    if let (Some(from), Some(to)) = (search.from, search.to) {
+        if from >= to {
+            return Err(format!("from ({}) must be before to ({})", from, to));
+        }
+    }
+    if let Some(cursor) = &search.cursor {
+        KillmailCursor::parse(cursor).ok_or_else(|| format!("Invalid cursor {}", cursor))?;
+    }
+    killmail_detail_processing::parse_page_limit(search.limit)
+}
+
+/// One page of `limit` killmails matching `search`, and on the first page how
+/// many match in total. The search should have been checked with
+/// `validate_search`.
+pub async fn search_killmails(
+    db: &DatabaseConnection,
+    search: &KillmailSearch,
+    limit: u64,
+) -> Result<KillmailSearchResults, DbErr> {
+    let condition = build_condition(search);
+    let mut query = Killmails::find().filter(condition.clone());
+    let total = match search.cursor.as_deref().and_then(KillmailCursor::parse) {
+        Some(cursor) => {
+            query = query.filter(after_cursor(cursor));
+            None
+        }
+        None => Some(Killmails::find().filter(condition).count(db).await?),
+    };
+    let mut keys: Vec<KillmailCursor> = query
+        .order_by_desc(killmails::Column::KillmailTime)
+        .order_by_desc(killmails::Column::KillmailId)
+        .limit(limit + 1)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|killmail| KillmailCursor {
+            killmail_time: killmail.killmail_time,
+            killmail_id: killmail.killmail_id,
+        })
+        .collect();
+    let next_cursor = killmail_detail_processing::take_page(&mut keys, limit);
+    Ok(KillmailSearchResults {
+        total,
+        killmails: killmail_detail_processing::get_killmail_summaries(
+            db,
+            keys.iter().map(|key| key.killmail_id).collect(),
+        )
+        .await?,
+        next_cursor,
+    })
+}
@@ -2,12 +2,33 @@
 extern crate log;
 extern crate dotenv;
 
+pub mod activity_processing;
+pub mod api_error;
+pub mod associates_processing;
+pub mod awox_processing;
+pub mod compare_processing;
+pub mod config;
+pub mod damage_processing;
 pub mod database;
 pub mod entity;
 pub mod esi;
+pub mod fleet_processing;
+pub mod gang_processing;
 pub mod jager_redis;
+pub mod killmail_detail_processing;
 pub mod killmail_processing;
+pub mod killmail_search_processing;
+pub mod location_processing;
 pub mod logging;
+pub mod name_processing;
 pub mod organization_processing;
+pub mod organization_stats_processing;
+pub mod retention;
+pub mod role_processing;
+pub mod search_processing;
+pub mod ship_processing;
 pub mod stats_processing;
+pub mod summary_processing;
+pub mod timeseries_processing;
+pub mod valuation_processing;
 pub mod zkill;
\ No newline at end of file
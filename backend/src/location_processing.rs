@@ -0,0 +1,344 @@
+use crate::config;
+use crate::config::StatsWindow;
+use crate::entity::prelude::*;
+use crate::entity::*;
+use crate::killmail_processing::ProcessingError;
+use crate::stats_processing;
+use crate::stats_processing::push_window_filter;
+use chrono::NaiveDateTime;
+use schemars::JsonSchema;
+use sea_orm::prelude::*;
+use sea_orm::{DatabaseConnection, DbBackend, DbErr, FromQueryResult, Statement, Value};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+const POCHVEN_REGION_ID: u64 = 10000070;
+const WORMHOLE_REGION_IDS: std::ops::RangeInclusive<u64> = 11000001..=11000033;
+const ABYSSAL_REGION_IDS: std::ops::RangeInclusive<u64> = 12000001..=12000005;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SpaceType {
+    HighSec,
+    LowSec,
+    NullSec,
+    Wormhole,
+    Pochven,
+    Abyssal,
+    /// The system isn't in the database yet, run the ESI harvester
+    Unknown,
+}
+
+impl SpaceType {
+    /// Security status is rounded to one decimal the way the game displays
+    /// it, so 0.45 counts as high-sec
+    pub fn classify(region_id: u64, security_status: f64) -> SpaceType {
+        if region_id == POCHVEN_REGION_ID {
+            SpaceType::Pochven
+        } else if WORMHOLE_REGION_IDS.contains(&region_id) {
+            SpaceType::Wormhole
+        } else if ABYSSAL_REGION_IDS.contains(&region_id) {
+            SpaceType::Abyssal
+        } else if security_status >= 0.45 {
+            SpaceType::HighSec
+        } else if security_status > 0.0 {
+            SpaceType::LowSec
+        } else {
+            SpaceType::NullSec
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct SpaceTypeActivity {
+    pub space_type: SpaceType,
+    pub kills: usize,
+    pub losses: usize,
+}
+
+/// Activity in a region or solar system
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct LocationActivity {
+    pub id: u64,
+    pub name: Option<String>,
+    pub kills: usize,
+    pub losses: usize,
+    pub last_seen: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+pub struct LocationProfile {
+    pub space_types: Vec<SpaceTypeActivity>,
+    pub regions: Vec<LocationActivity>,
+    pub systems: Vec<LocationActivity>,
+}
+
+struct SystemInfo {
+    name: String,
+    region_id: u64,
+    region_name: Option<String>,
+    space_type: SpaceType,
+}
+
+async fn get_system_info(
+    db: &DatabaseConnection,
+    system_ids: Vec<u64>,
+) -> Result<HashMap<u64, SystemInfo>, DbErr> {
+    if system_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let systems = SolarSystems::find()
+        .filter(solar_systems::Column::SystemId.is_in(system_ids))
+        .all(db)
+        .await?;
+    let constellation_ids: Vec<u64> = systems
+        .iter()
+        .map(|system| system.constellation_id)
+        .collect();
+    let constellations: HashMap<u64, u64> = Constellations::find()
+        .filter(constellations::Column::ConstellationId.is_in(constellation_ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|constellation| (constellation.constellation_id, constellation.region_id))
+        .collect();
+    let region_ids: Vec<u64> = constellations.values().cloned().collect();
+    let regions: HashMap<u64, String> = Regions::find()
+        .filter(regions::Column::RegionId.is_in(region_ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|region| (region.region_id, region.name))
+        .collect();
+    Ok(systems
+        .into_iter()
+        .filter_map(|system| {
+            let region_id = *constellations.get(&system.constellation_id)?;
+            Some((
+                system.system_id,
+                SystemInfo {
+                    name: system.name,
+                    region_id,
+                    region_name: regions.get(&region_id).cloned(),
+                    space_type: SpaceType::classify(region_id, system.security_status),
+                },
+            ))
+        })
+        .collect())
+}
+
+#[derive(Default)]
+struct LocationTally {
+    counts: HashMap<u64, (usize, usize, NaiveDateTime)>,
+}
+
+impl LocationTally {
+    fn add(&mut self, id: u64, is_loss: bool, count: usize, time: NaiveDateTime) {
+        let entry = self.counts.entry(id).or_insert((0, 0, time));
+        if is_loss {
+            entry.1 += count;
+        } else {
+            entry.0 += count;
+        }
+        if time > entry.2 {
+            entry.2 = time;
+        }
+    }
+
+    /// The `limit` busiest locations, most recent first on ties
+    fn top(self, limit: usize, names: impl Fn(u64) -> Option<String>) -> Vec<LocationActivity> {
+        let mut locations: Vec<LocationActivity> = self
+            .counts
+            .into_iter()
+            .map(|(id, (kills, losses, last_seen))| LocationActivity {
+                id,
+                name: names(id),
+                kills,
+                losses,
+                last_seen,
+            })
+            .collect();
+        locations.sort_by(|a, b| {
+            (b.kills + b.losses)
+                .cmp(&(a.kills + a.losses))
+                .then(b.last_seen.cmp(&a.last_seen))
+        });
+        locations.truncate(limit);
+        locations
+    }
+}
+
+#[derive(Debug, FromQueryResult)]
+struct SystemCount {
+    character_id: u64,
+    is_loss: i32,
+    solar_system_id: u64,
+    count: i64,
+    last_seen: NaiveDateTime,
+}
+
+fn get_location_profile(
+    system_counts: Vec<SystemCount>,
+    system_info: &HashMap<u64, SystemInfo>,
+    region_names: &HashMap<u64, String>,
+) -> LocationProfile {
+    let mut space_types: HashMap<SpaceType, (usize, usize)> = HashMap::new();
+    let mut regions = LocationTally::default();
+    let mut systems = LocationTally::default();
+    for system_count in system_counts {
+        let is_loss = system_count.is_loss == 1;
+        let count = system_count.count as usize;
+        let info = system_info.get(&system_count.solar_system_id);
+        let space_type = info.map_or(SpaceType::Unknown, |info| info.space_type);
+        let entry = space_types.entry(space_type).or_insert((0, 0));
+        if is_loss {
+            entry.1 += count;
+        } else {
+            entry.0 += count;
+        }
+        if let Some(info) = info {
+            regions.add(info.region_id, is_loss, count, system_count.last_seen);
+        }
+        systems.add(
+            system_count.solar_system_id,
+            is_loss,
+            count,
+            system_count.last_seen,
+        );
+    }
+    let mut space_types: Vec<SpaceTypeActivity> = space_types
+        .into_iter()
+        .map(|(space_type, (kills, losses))| SpaceTypeActivity {
+            space_type,
+            kills,
+            losses,
+        })
+        .collect();
+    space_types.sort_by_key(|space_type| Reverse(space_type.kills + space_type.losses));
+    let limit = config::get().stats.top_entries;
+    LocationProfile {
+        space_types,
+        regions: regions.top(limit, |id| region_names.get(&id).cloned()),
+        systems: systems.top(limit, |id| {
+            system_info.get(&id).map(|info| info.name.clone())
+        }),
+    }
+}
+
+/// Where each character gets kills and dies inside `window`. The database
+/// counts killmails per system, regions and space types are summed from those.
+pub async fn get_location_profiles(
+    db: &DatabaseConnection,
+    character_ids: &[u64],
+    window: &StatsWindow,
+) -> Result<HashMap<u64, LocationProfile>, DbErr> {
+    if character_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let mut values: Vec<Value> = Vec::new();
+    let mut sql = format!(
+        r#"SELECT c.character_id AS character_id, c.is_loss AS is_loss,
+            k.solar_system_id AS solar_system_id, COUNT(*) AS count,
+            MAX(k.killmail_time) AS last_seen
+        FROM ({}) c
+        JOIN killmails k ON k.killmail_id = c.killmail_id
+        WHERE 1 = 1"#,
+        stats_processing::character_killmails_sql(&mut values, character_ids)
+    );
+    push_window_filter(&mut sql, &mut values, window);
+    sql.push_str(" GROUP BY c.character_id, c.is_loss, k.solar_system_id");
+    let mut system_counts: HashMap<u64, Vec<SystemCount>> = HashMap::new();
+    for system_count in SystemCount::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::MySql,
+        &sql,
+        values,
+    ))
+    .all(db)
+    .await?
+    {
+        system_counts
+            .entry(system_count.character_id)
+            .or_default()
+            .push(system_count);
+    }
+    let mut system_ids: Vec<u64> = system_counts
+        .values()
+        .flatten()
+        .map(|system_count| system_count.solar_system_id)
+        .collect();
+    system_ids.sort_unstable();
+    system_ids.dedup();
+    let system_info = get_system_info(db, system_ids).await?;
+    let region_names: HashMap<u64, String> = system_info
+        .values()
+        .filter_map(|info| Some((info.region_id, info.region_name.clone()?)))
+        .collect();
+    Ok(character_ids
+        .iter()
+        .map(|character_id| {
+            (
+                *character_id,
+                get_location_profile(
+                    system_counts.remove(character_id).unwrap_or_default(),
+                    &system_info,
+                    &region_names,
+                ),
+            )
+        })
+        .collect())
+}
+
+pub async fn get_character_locations(
+    db: &DatabaseConnection,
+    name: String,
+    window: &StatsWindow,
+) -> Result<Option<LocationProfile>, ProcessingError> {
+    match stats_processing::get_or_update_character_public_info(db, name).await? {
+        Some(char_info) => {
+            let character_id = char_info.character_id;
+            let mut profiles = get_location_profiles(db, &[character_id], window).await?;
+            Ok(profiles.remove(&character_id))
+        }
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The Forge, a k-space region
+    const K_SPACE_REGION_ID: u64 = 10000002;
+
+    #[test]
+    fn classifies_by_displayed_security() {
+        for (security_status, space_type) in [
+            (1.0, SpaceType::HighSec),
+            (0.45, SpaceType::HighSec),
+            (0.44, SpaceType::LowSec),
+            (0.05, SpaceType::LowSec),
+            (0.0, SpaceType::NullSec),
+            (-0.99, SpaceType::NullSec),
+        ] {
+            assert_eq!(
+                SpaceType::classify(K_SPACE_REGION_ID, security_status),
+                space_type,
+                "security {}",
+                security_status
+            );
+        }
+    }
+
+    #[test]
+    fn special_regions_ignore_security() {
+        assert_eq!(
+            SpaceType::classify(POCHVEN_REGION_ID, -1.0),
+            SpaceType::Pochven
+        );
+        assert_eq!(SpaceType::classify(11000001, -0.99), SpaceType::Wormhole);
+        assert_eq!(SpaceType::classify(11000033, -0.99), SpaceType::Wormhole);
+        assert_eq!(SpaceType::classify(12000005, -0.99), SpaceType::Abyssal);
+        assert_eq!(SpaceType::classify(11000034, -0.99), SpaceType::NullSec);
+    }
+}
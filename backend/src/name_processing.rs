@@ -0,0 +1,152 @@
+use crate::database;
+use crate::entity::prelude::*;
+use crate::entity::*;
+use chrono::{DateTime, Utc};
+use sea_orm::prelude::*;
+use sea_orm::{
+    ConnectionTrait, DatabaseConnection, DatabaseTransaction, DbErr, Set, TransactionTrait,
+};
+use std::collections::HashMap;
+
+/// Result of looking a character or organization up by name
+#[derive(Debug)]
+pub enum NameLookup {
+    Found(u64),
+    NotFound,
+    /// More than one match. For characters this usually means one of them has
+    /// been renamed or biomassed and we haven't refreshed it yet.
+    Ambiguous(Vec<u64>),
+}
+
+impl NameLookup {
+    pub fn from_ids(mut ids: Vec<u64>) -> NameLookup {
+        ids.sort_unstable();
+        ids.dedup();
+        match ids.len() {
+            0 => NameLookup::NotFound,
+            1 => NameLookup::Found(ids[0]),
+            _ => NameLookup::Ambiguous(ids),
+        }
+    }
+}
+
+async fn get_current_names<C: ConnectionTrait>(
+    db: &C,
+    name: &str,
+) -> Result<Vec<character_name_history::Model>, DbErr> {
+    // character_name uses a case-insensitive collation, so this matches
+    // regardless of how the name was typed
+    CharacterNameHistory::find()
+        .filter(character_name_history::Column::CharacterName.eq(name))
+        .filter(character_name_history::Column::ObservedTo.is_null())
+        .all(db)
+        .await
+}
+
+/// Find the character currently using `name`
+pub async fn lookup_character_name(
+    db: &DatabaseConnection,
+    name: &str,
+) -> Result<NameLookup, DbErr> {
+    let character_ids: Vec<u64> = get_current_names(db, name)
+        .await?
+        .into_iter()
+        .map(|observation| observation.character_id)
+        .collect();
+    Ok(NameLookup::from_ids(character_ids))
+}
+
+/// Look up many names at once, keyed by the lowercased name since the
+/// database doesn't care how they were typed
+pub async fn lookup_character_names(
+    db: &DatabaseConnection,
+    names: &[String],
+) -> Result<HashMap<String, NameLookup>, DbErr> {
+    if names.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let mut character_ids: HashMap<String, Vec<u64>> = HashMap::new();
+    for observation in CharacterNameHistory::find()
+        .filter(character_name_history::Column::CharacterName.is_in(names.to_vec()))
+        .filter(character_name_history::Column::ObservedTo.is_null())
+        .all(db)
+        .await?
+    {
+        character_ids
+            .entry(observation.character_name.to_lowercase())
+            .or_default()
+            .push(observation.character_id);
+    }
+    Ok(names
+        .iter()
+        .map(|name| {
+            let name = name.to_lowercase();
+            let lookup =
+                NameLookup::from_ids(character_ids.get(&name).cloned().unwrap_or_default());
+            (name, lookup)
+        })
+        .collect())
+}
+
+async fn close_observation(
+    txn: &DatabaseTransaction,
+    observation: character_name_history::Model,
+) -> Result<(), DbErr> {
+    let mut active: character_name_history::ActiveModel = observation.into();
+    active.observed_to = Set(Some(DateTime::naive_utc(&Utc::now())));
+    active.update(txn).await?;
+    Ok(())
+}
+
+/// Record that `character_id` was seen using `name`. If the character was
+/// previously known by another name that observation is closed, as is any
+/// open observation of the same name by a different character, since only
+/// one character can hold a name at a time. Closing and opening happen in one
+/// transaction, and a unique index keeps a character to one open observation,
+/// so concurrent ingests of the same character record it once.
+pub async fn record_character_name(
+    db: &DatabaseConnection,
+    character_id: u64,
+    name: &str,
+) -> Result<(), DbErr> {
+    let txn = db.begin().await?;
+    let current = CharacterNameHistory::find()
+        .filter(character_name_history::Column::CharacterId.eq(character_id))
+        .filter(character_name_history::Column::ObservedTo.is_null())
+        .all(&txn)
+        .await?;
+    let mut already_current = false;
+    for observation in current {
+        if observation.character_name == name && !already_current {
+            already_current = true;
+        } else {
+            info!(
+                "Character {} renamed from {} to {}",
+                character_id, observation.character_name, name
+            );
+            close_observation(&txn, observation).await?;
+        }
+    }
+    if !already_current {
+        for observation in get_current_names(&txn, name).await? {
+            if observation.character_id != character_id {
+                info!(
+                    "Name {} moved from character {} to {}",
+                    name, observation.character_id, character_id
+                );
+                close_observation(&txn, observation).await?;
+            }
+        }
+        let inserted =
+            character_name_history::ActiveModel::new_observation(character_id, name.to_string())
+                .insert(&txn)
+                .await;
+        match inserted {
+            Ok(_) => {}
+            // Another ingest opened an observation for the character first
+            Err(err) if database::is_duplicate_err(&err) => return Ok(()),
+            Err(err) => return Err(err),
+        }
+    }
+    txn.commit().await
+}
@@ -1,13 +1,14 @@
+use crate::config;
 use crate::database;
 use crate::entity::prelude::*;
 use crate::entity::*;
 use crate::esi;
 use crate::killmail_processing::ProcessingError;
+use crate::name_processing;
+use crate::name_processing::NameLookup;
 use futures::{stream, StreamExt};
 use sea_orm::prelude::*;
-use sea_orm::DatabaseConnection;
-
-const CONCURRENCY: usize = 10;
+use sea_orm::{ActiveValue, DatabaseConnection, DbErr};
 
 pub async fn store_alliance_if_not_present(
     db: &DatabaseConnection,
@@ -32,7 +33,7 @@ pub async fn store_alliances_if_not_present(
     let mut err: Option<ProcessingError> = Option::None;
     let mut bodies = stream::iter(alliance_ids)
         .map(|id| async move { store_alliance_if_not_present(db, id).await })
-        .buffer_unordered(CONCURRENCY);
+        .buffer_unordered(config::get().concurrency.organizations);
     while let Some(item) = bodies.next().await {
         match item {
             Ok(_) => {}
@@ -75,7 +76,7 @@ pub async fn store_corporations_if_not_present(
     let mut err: Option<ProcessingError> = Option::None;
     let mut bodies = stream::iter(corporation_ids)
         .map(|id| async move { store_corporation_if_not_present(db, id).await })
-        .buffer_unordered(CONCURRENCY);
+        .buffer_unordered(config::get().concurrency.organizations);
     while let Some(item) = bodies.next().await {
         match item {
             Ok(_) => {}
@@ -103,7 +104,18 @@ pub async fn store_pubchar_info_if_not_present(
     if pubchar_result.is_none() {
         info!("Pubchar info for {} not found, fetching from esi", char_id);
         let pubchar_insertable = esi::get_character(char_id).await?;
+        let character_name = match &pubchar_insertable.character_name {
+            ActiveValue::Set(name) | ActiveValue::Unchanged(name) => Some(name.clone()),
+            ActiveValue::NotSet => None,
+        };
         database::insert_pubchar_info_if_not_present(db, pubchar_insertable).await?;
+        // Another task may have stored the character first and then failed
+        // before recording its name. Recording the name is idempotent, so do
+        // it either way.
+        match character_name {
+            Some(name) => name_processing::record_character_name(db, char_id, &name).await?,
+            None => warn!("ESI returned character {} without a name", char_id),
+        }
     }
     Ok(())
 }
@@ -115,7 +127,7 @@ pub async fn store_pubchars_info_if_not_present(
     let mut err: Option<ProcessingError> = Option::None;
     let mut bodies = stream::iter(pubchar_ids)
         .map(|id| async move { store_pubchar_info_if_not_present(db, id).await })
-        .buffer_unordered(CONCURRENCY);
+        .buffer_unordered(config::get().concurrency.organizations);
     while let Some(item) = bodies.next().await {
         match item {
             Ok(_) => {}
@@ -131,3 +143,90 @@ pub async fn store_pubchars_info_if_not_present(
         Ok(())
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrganizationKind {
+    Corporation,
+    Alliance,
+}
+
+impl OrganizationKind {
+    /// The column holding this kind of organization on attackers and victims
+    pub fn column_name(&self) -> &'static str {
+        match self {
+            OrganizationKind::Corporation => "corporation_id",
+            OrganizationKind::Alliance => "alliance_id",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OrganizationKind::Corporation => "corporation",
+            OrganizationKind::Alliance => "alliance",
+        }
+    }
+}
+
+/// Find a corporation by name, falling back to its ticker. Names and tickers
+/// are compared case-insensitively by the column collation.
+pub async fn lookup_corporation(
+    db: &DatabaseConnection,
+    name_or_ticker: &str,
+) -> Result<NameLookup, DbErr> {
+    let by_name = Corporations::find()
+        .filter(corporations::Column::Name.eq(name_or_ticker))
+        .all(db)
+        .await?;
+    let corporation_ids: Vec<u64> = if by_name.is_empty() {
+        Corporations::find()
+            .filter(corporations::Column::Ticker.eq(name_or_ticker))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|corporation| corporation.corporation_id)
+            .collect()
+    } else {
+        by_name
+            .into_iter()
+            .map(|corporation| corporation.corporation_id)
+            .collect()
+    };
+    Ok(NameLookup::from_ids(corporation_ids))
+}
+
+/// Find an alliance by name, falling back to its ticker
+pub async fn lookup_alliance(
+    db: &DatabaseConnection,
+    name_or_ticker: &str,
+) -> Result<NameLookup, DbErr> {
+    let by_name = Alliances::find()
+        .filter(alliances::Column::Name.eq(name_or_ticker))
+        .all(db)
+        .await?;
+    let alliance_ids: Vec<u64> = if by_name.is_empty() {
+        Alliances::find()
+            .filter(alliances::Column::Ticker.eq(name_or_ticker))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|alliance| alliance.alliance_id)
+            .collect()
+    } else {
+        by_name
+            .into_iter()
+            .map(|alliance| alliance.alliance_id)
+            .collect()
+    };
+    Ok(NameLookup::from_ids(alliance_ids))
+}
+
+pub async fn lookup_organization(
+    db: &DatabaseConnection,
+    kind: OrganizationKind,
+    name_or_ticker: &str,
+) -> Result<NameLookup, DbErr> {
+    match kind {
+        OrganizationKind::Corporation => lookup_corporation(db, name_or_ticker).await,
+        OrganizationKind::Alliance => lookup_alliance(db, name_or_ticker).await,
+    }
+}
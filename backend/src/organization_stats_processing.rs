@@ -0,0 +1,298 @@
+use crate::activity_processing;
+use crate::activity_processing::ActivityProfile;
+use crate::config;
+use crate::config::StatsWindow;
+use crate::damage_processing;
+use crate::damage_processing::DamageStats;
+use crate::entity::prelude::*;
+use crate::entity::*;
+use crate::killmail_processing::ProcessingError;
+use crate::name_processing::NameLookup;
+use crate::organization_processing;
+use crate::organization_processing::OrganizationKind;
+use crate::ship_processing;
+use crate::ship_processing::TypeUsage;
+use crate::stats_processing::push_window_filter;
+use crate::stats_processing::KillLossRatio;
+use chrono::NaiveDateTime;
+use schemars::JsonSchema;
+use sea_orm::prelude::*;
+use sea_orm::{DatabaseConnection, DbBackend, DbErr, FromQueryResult, Statement, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// A member and how many of the organization's kills they were on
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct PilotKills {
+    pub character_id: u64,
+    pub character_name: Option<String>,
+    pub kills: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct OrganizationStats {
+    pub organization_id: u64,
+    pub name: String,
+    pub ticker: String,
+    pub window: String,
+    pub kill_loss_ratio: KillLossRatio,
+    pub solo_kill_loss_ratio: KillLossRatio,
+    /// Members seen on a killmail inside the window
+    pub active_members: usize,
+    pub top_pilots: Vec<PilotKills>,
+    pub top_ships: Vec<TypeUsage>,
+    pub activity: ActivityProfile,
+    pub damage: DamageStats,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct KillmailCount {
+    count: i64,
+    solo: i64,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct MemberCount {
+    count: i64,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct PilotKillCount {
+    character_id: u64,
+    kills: i64,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct ShipCount {
+    type_id: u64,
+    count: i64,
+    last_used: NaiveDateTime,
+}
+
+/// Killmails with a member on `table` (attackers or victims), and how many of
+/// them had a single player attacker
+async fn get_killmail_count(
+    db: &DatabaseConnection,
+    kind: OrganizationKind,
+    organization_id: u64,
+    window: &StatsWindow,
+    table: &str,
+) -> Result<KillmailCount, DbErr> {
+    let mut sql = format!(
+        r#"SELECT COUNT(*) AS count,
+            CAST(COALESCE(SUM((
+                SELECT COUNT(p.character_id) FROM attackers p WHERE p.killmail_id = k.killmail_id
+            ) = 1), 0) AS SIGNED) AS solo
+        FROM killmails k
+        WHERE k.killmail_id IN (SELECT killmail_id FROM {table} WHERE {column} = ?)"#,
+        table = table,
+        column = kind.column_name()
+    );
+    let mut values: Vec<Value> = vec![organization_id.into()];
+    push_window_filter(&mut sql, &mut values, window);
+    let count = KillmailCount::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::MySql,
+        &sql,
+        values,
+    ))
+    .one(db)
+    .await?;
+    Ok(count.unwrap_or(KillmailCount { count: 0, solo: 0 }))
+}
+
+async fn get_active_members(
+    db: &DatabaseConnection,
+    kind: OrganizationKind,
+    organization_id: u64,
+    window: &StatsWindow,
+) -> Result<usize, DbErr> {
+    let mut sql = format!(
+        r#"SELECT COUNT(DISTINCT m.character_id) AS count
+        FROM (
+            SELECT character_id, killmail_id FROM attackers
+                WHERE {column} = ? AND character_id IS NOT NULL
+            UNION ALL
+            SELECT character_id, killmail_id FROM victims
+                WHERE {column} = ? AND character_id IS NOT NULL
+        ) m
+        JOIN killmails k ON k.killmail_id = m.killmail_id
+        WHERE 1 = 1"#,
+        column = kind.column_name()
+    );
+    let mut values: Vec<Value> = vec![organization_id.into(), organization_id.into()];
+    push_window_filter(&mut sql, &mut values, window);
+    let count = MemberCount::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::MySql,
+        &sql,
+        values,
+    ))
+    .one(db)
+    .await?;
+    Ok(count.map(|count| count.count as usize).unwrap_or(0))
+}
+
+async fn get_top_pilots(
+    db: &DatabaseConnection,
+    kind: OrganizationKind,
+    organization_id: u64,
+    window: &StatsWindow,
+) -> Result<Vec<PilotKills>, DbErr> {
+    let mut sql = format!(
+        r#"SELECT a.character_id AS character_id, COUNT(DISTINCT a.killmail_id) AS kills
+        FROM attackers a
+        JOIN killmails k ON k.killmail_id = a.killmail_id
+        WHERE a.{column} = ? AND a.character_id IS NOT NULL"#,
+        column = kind.column_name()
+    );
+    let mut values: Vec<Value> = vec![organization_id.into()];
+    push_window_filter(&mut sql, &mut values, window);
+    sql.push_str(" GROUP BY a.character_id ORDER BY kills DESC LIMIT ?");
+    values.push((config::get().stats.top_entries as u64).into());
+    let counts = PilotKillCount::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::MySql,
+        &sql,
+        values,
+    ))
+    .all(db)
+    .await?;
+    let character_ids: Vec<u64> = counts.iter().map(|count| count.character_id).collect();
+    let names: HashMap<u64, String> = if character_ids.is_empty() {
+        HashMap::new()
+    } else {
+        CharacterPublicInfo::find()
+            .filter(character_public_info::Column::CharacterId.is_in(character_ids))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|character| (character.character_id, character.character_name))
+            .collect()
+    };
+    Ok(counts
+        .into_iter()
+        .map(|count| PilotKills {
+            character_id: count.character_id,
+            character_name: names.get(&count.character_id).cloned(),
+            kills: count.kills as usize,
+        })
+        .collect())
+}
+
+async fn get_top_ships(
+    db: &DatabaseConnection,
+    kind: OrganizationKind,
+    organization_id: u64,
+    window: &StatsWindow,
+) -> Result<Vec<TypeUsage>, DbErr> {
+    let mut sql = format!(
+        r#"SELECT a.ship_type_id AS type_id, COUNT(*) AS count, MAX(k.killmail_time) AS last_used
+        FROM attackers a
+        JOIN killmails k ON k.killmail_id = a.killmail_id
+        WHERE a.{column} = ? AND a.ship_type_id IS NOT NULL"#,
+        column = kind.column_name()
+    );
+    let mut values: Vec<Value> = vec![organization_id.into()];
+    push_window_filter(&mut sql, &mut values, window);
+    sql.push_str(" GROUP BY a.ship_type_id ORDER BY count DESC, last_used DESC LIMIT ?");
+    values.push((config::get().stats.top_entries as u64).into());
+    let counts: Vec<(u64, usize, NaiveDateTime)> = ShipCount::find_by_statement(
+        Statement::from_sql_and_values(DbBackend::MySql, &sql, values),
+    )
+    .all(db)
+    .await?
+    .into_iter()
+    .map(|count| (count.type_id, count.count as usize, count.last_used))
+    .collect();
+    let type_ids: Vec<u64> = counts.iter().map(|(type_id, _, _)| *type_id).collect();
+    let type_info = ship_processing::get_type_info(db, type_ids).await?;
+    Ok(ship_processing::to_type_usage(counts, &type_info))
+}
+
+/// Name and ticker of a corporation or alliance
+async fn get_organization_names(
+    db: &DatabaseConnection,
+    kind: OrganizationKind,
+    organization_id: u64,
+) -> Result<Option<(String, String)>, DbErr> {
+    Ok(match kind {
+        OrganizationKind::Corporation => Corporations::find_by_id(organization_id)
+            .one(db)
+            .await?
+            .map(|corporation| (corporation.name, corporation.ticker)),
+        OrganizationKind::Alliance => Alliances::find_by_id(organization_id)
+            .one(db)
+            .await?
+            .map(|alliance| (alliance.name, alliance.ticker)),
+    })
+}
+
+/// Resolve a corporation or alliance name or ticker to its id
+pub async fn get_organization_id(
+    db: &DatabaseConnection,
+    kind: OrganizationKind,
+    name_or_ticker: &str,
+) -> Result<Option<u64>, ProcessingError> {
+    match organization_processing::lookup_organization(db, kind, name_or_ticker).await? {
+        NameLookup::Found(organization_id) => Ok(Some(organization_id)),
+        NameLookup::NotFound => Ok(None),
+        NameLookup::Ambiguous(organization_ids) => {
+            warn!(
+                "{} matches organizations {:?}",
+                name_or_ticker, organization_ids
+            );
+            Err(ProcessingError::AmbiguousOrganizationName(organization_ids))
+        }
+    }
+}
+
+/// Aggregate stats for a corporation or alliance from every killmail its
+/// members appear on as attackers or victims
+pub async fn get_organization_stats(
+    db: &DatabaseConnection,
+    kind: OrganizationKind,
+    name_or_ticker: &str,
+    window: &StatsWindow,
+) -> Result<Option<OrganizationStats>, ProcessingError> {
+    let start_time = Instant::now();
+    let organization_id = match get_organization_id(db, kind, name_or_ticker).await? {
+        Some(organization_id) => organization_id,
+        None => return Ok(None),
+    };
+    let (name, ticker) = match get_organization_names(db, kind, organization_id).await? {
+        Some(names) => names,
+        None => return Ok(None),
+    };
+    let kills = get_killmail_count(db, kind, organization_id, window, "attackers").await?;
+    let losses = get_killmail_count(db, kind, organization_id, window, "victims").await?;
+    let active_members = get_active_members(db, kind, organization_id, window).await?;
+    let top_pilots = get_top_pilots(db, kind, organization_id, window).await?;
+    let top_ships = get_top_ships(db, kind, organization_id, window).await?;
+    let activity =
+        activity_processing::get_organization_activity(db, kind, organization_id, window).await?;
+    let damage =
+        damage_processing::get_organization_damage_stats(db, kind, organization_id, window).await?;
+    info!(
+        "Stats for {} took {}ms",
+        name,
+        start_time.elapsed().as_millis()
+    );
+    Ok(Some(OrganizationStats {
+        organization_id,
+        name,
+        ticker,
+        window: window.name.clone(),
+        kill_loss_ratio: KillLossRatio {
+            kills: kills.count as usize,
+            losses: losses.count as usize,
+        },
+        solo_kill_loss_ratio: KillLossRatio {
+            kills: kills.solo as usize,
+            losses: losses.solo as usize,
+        },
+        active_members,
+        top_pilots,
+        top_ships,
+        activity,
+        damage,
+    }))
+}
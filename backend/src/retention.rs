@@ -0,0 +1,299 @@
+use crate::entity::prelude::*;
+use crate::entity::*;
+use crate::summary_processing;
+use chrono::{Duration, NaiveDateTime, Utc};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use sea_orm::prelude::*;
+use sea_orm::{
+    ConnectionTrait, DatabaseConnection, DbBackend, DbErr, QueryOrder, QuerySelect, Statement,
+    Value,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+const PRUNE_BATCH_SIZE: u64 = 1000;
+
+#[derive(Debug)]
+pub enum RetentionError {
+    DBError(DbErr),
+    IOError(std::io::Error),
+    Serialize(serde_json::Error),
+}
+
+impl From<DbErr> for RetentionError {
+    fn from(err: DbErr) -> RetentionError {
+        RetentionError::DBError(err)
+    }
+}
+
+impl From<std::io::Error> for RetentionError {
+    fn from(err: std::io::Error) -> RetentionError {
+        RetentionError::IOError(err)
+    }
+}
+
+impl From<serde_json::Error> for RetentionError {
+    fn from(err: serde_json::Error) -> RetentionError {
+        RetentionError::Serialize(err)
+    }
+}
+
+/// Retention windows, in days, for each table that can be pruned.
+///
+/// Attackers, victims, items, positions and values are removed along with
+/// their killmail through `ON DELETE CASCADE`, so they don't have a window of
+/// their own. A window of `None` keeps rows forever. Corporations and
+/// alliances carry no timestamp, so they are only removed once nothing
+/// references them. The latest market price of each type is always kept.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    pub killmail_days: Option<i64>,
+    pub character_days: Option<i64>,
+    pub market_price_days: Option<i64>,
+    pub prune_orphaned_organizations: bool,
+}
+
+fn get_cutoff(days: i64) -> NaiveDateTime {
+    Utc::now().naive_utc() - Duration::days(days)
+}
+
+#[derive(Debug, Default)]
+pub struct PruneReport {
+    pub killmails: u64,
+    pub characters: u64,
+    pub market_prices: u64,
+    pub corporations: u64,
+    pub alliances: u64,
+}
+
+#[derive(Serialize)]
+struct ArchivedKillmail {
+    killmail: killmails::Model,
+    victim: Option<victims::Model>,
+    attackers: Vec<attackers::Model>,
+    items: Vec<victim_items::Model>,
+    position: Option<killmail_positions::Model>,
+    value: Option<killmail_values::Model>,
+}
+
+async fn get_archived_killmails(
+    db: &DatabaseConnection,
+    killmails: Vec<killmails::Model>,
+) -> Result<Vec<ArchivedKillmail>, DbErr> {
+    let killmail_ids: Vec<u64> = killmails.iter().map(|km| km.killmail_id).collect();
+    let mut victims: HashMap<u64, victims::Model> = Victims::find()
+        .filter(victims::Column::KillmailId.is_in(killmail_ids.clone()))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|victim| (victim.killmail_id, victim))
+        .collect();
+    let mut positions: HashMap<u64, killmail_positions::Model> = KillmailPositions::find()
+        .filter(killmail_positions::Column::KillmailId.is_in(killmail_ids.clone()))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|position| (position.killmail_id, position))
+        .collect();
+    let mut values: HashMap<u64, killmail_values::Model> = KillmailValues::find()
+        .filter(killmail_values::Column::KillmailId.is_in(killmail_ids.clone()))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|value| (value.killmail_id, value))
+        .collect();
+    let mut items: HashMap<u64, Vec<victim_items::Model>> = HashMap::new();
+    for item in VictimItems::find()
+        .filter(victim_items::Column::KillmailId.is_in(killmail_ids.clone()))
+        .all(db)
+        .await?
+    {
+        items.entry(item.killmail_id).or_default().push(item);
+    }
+    let mut attackers: HashMap<u64, Vec<attackers::Model>> = HashMap::new();
+    for attacker in Attackers::find()
+        .filter(attackers::Column::KillmailId.is_in(killmail_ids))
+        .all(db)
+        .await?
+    {
+        attackers
+            .entry(attacker.killmail_id)
+            .or_default()
+            .push(attacker);
+    }
+    Ok(killmails
+        .into_iter()
+        .map(|killmail| ArchivedKillmail {
+            victim: victims.remove(&killmail.killmail_id),
+            attackers: attackers.remove(&killmail.killmail_id).unwrap_or_default(),
+            items: items.remove(&killmail.killmail_id).unwrap_or_default(),
+            position: positions.remove(&killmail.killmail_id),
+            value: values.remove(&killmail.killmail_id),
+            killmail,
+        })
+        .collect())
+}
+
+/// Delete killmails older than `days`, writing them to a gzipped JSONL file
+/// at `archive_path` first if one is given. The daily stats are brought in
+/// line afterwards, so windows never count pruned killmails.
+pub async fn prune_killmails(
+    db: &DatabaseConnection,
+    days: i64,
+    archive_path: Option<&Path>,
+) -> Result<u64, RetentionError> {
+    let cutoff = get_cutoff(days);
+    info!("Pruning killmails older than {}", cutoff);
+    let mut archive = match archive_path {
+        Some(path) => Some(GzEncoder::new(
+            BufWriter::new(File::create(path)?),
+            Compression::default(),
+        )),
+        None => None,
+    };
+    let mut pruned: u64 = 0;
+    loop {
+        let batch = Killmails::find()
+            .filter(killmails::Column::KillmailTime.lt(cutoff))
+            .order_by_asc(killmails::Column::KillmailId)
+            .limit(PRUNE_BATCH_SIZE)
+            .all(db)
+            .await?;
+        if batch.is_empty() {
+            break;
+        }
+        let killmail_ids: Vec<u64> = batch.iter().map(|km| km.killmail_id).collect();
+        if let Some(writer) = archive.as_mut() {
+            for archived in get_archived_killmails(db, batch).await? {
+                serde_json::to_writer(&mut *writer, &archived)?;
+                writer.write_all(b"\n")?;
+            }
+        }
+        let result = Killmails::delete_many()
+            .filter(killmails::Column::KillmailId.is_in(killmail_ids))
+            .exec(db)
+            .await?;
+        pruned += result.rows_affected;
+        info!("Pruned {} killmails so far", pruned);
+    }
+    if let Some(writer) = archive {
+        writer.finish()?.flush()?;
+    }
+    if pruned > 0 {
+        info!("Recomputing daily stats up to {}", cutoff);
+        summary_processing::forget_before(db, cutoff).await?;
+    }
+    Ok(pruned)
+}
+
+async fn execute_delete(
+    db: &DatabaseConnection,
+    sql: &str,
+    cutoff: Option<NaiveDateTime>,
+) -> Result<u64, DbErr> {
+    let values: Vec<Value> = match cutoff {
+        Some(cutoff) => vec![cutoff.into()],
+        None => vec![],
+    };
+    let result = db
+        .execute(Statement::from_sql_and_values(
+            DbBackend::MySql,
+            sql,
+            values,
+        ))
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Delete characters that no longer appear on any killmail. Characters
+/// refreshed within the last `days` are kept.
+pub async fn prune_orphaned_characters(db: &DatabaseConnection, days: i64) -> Result<u64, DbErr> {
+    execute_delete(
+        db,
+        r#"DELETE FROM character_public_info
+        WHERE NOT EXISTS (SELECT 1 FROM attackers a WHERE a.character_id = character_public_info.character_id)
+            AND NOT EXISTS (SELECT 1 FROM victims v WHERE v.character_id = character_public_info.character_id)
+            AND (last_updated IS NULL OR last_updated < ?)"#,
+        Some(get_cutoff(days)),
+    )
+    .await
+}
+
+/// Delete market price snapshots older than `days`, except the latest one of
+/// each type, which valuations still need
+pub async fn prune_market_prices(db: &DatabaseConnection, days: i64) -> Result<u64, DbErr> {
+    execute_delete(
+        db,
+        r#"DELETE p FROM market_prices p
+        JOIN market_prices newer ON newer.type_id = p.type_id AND newer.observed_at > p.observed_at
+        WHERE p.observed_at < ?"#,
+        Some(get_cutoff(days)),
+    )
+    .await
+}
+
+/// Delete corporations that no longer appear on any killmail and that no
+/// remaining character or faction points at
+pub async fn prune_orphaned_corporations(db: &DatabaseConnection) -> Result<u64, DbErr> {
+    execute_delete(
+        db,
+        r#"DELETE FROM corporations
+        WHERE NOT EXISTS (SELECT 1 FROM attackers a WHERE a.corporation_id = corporations.corporation_id)
+            AND NOT EXISTS (SELECT 1 FROM victims v WHERE v.corporation_id = corporations.corporation_id)
+            AND NOT EXISTS (SELECT 1 FROM character_public_info c WHERE c.corporation_id = corporations.corporation_id)
+            AND NOT EXISTS (SELECT 1 FROM factions f WHERE f.corporation_id = corporations.corporation_id
+                OR f.militia_corporation_id = corporations.corporation_id)"#,
+        None,
+    )
+    .await
+}
+
+/// Delete alliances that no longer appear on any killmail and that no
+/// remaining character or corporation points at
+pub async fn prune_orphaned_alliances(db: &DatabaseConnection) -> Result<u64, DbErr> {
+    execute_delete(
+        db,
+        r#"DELETE FROM alliances
+        WHERE NOT EXISTS (SELECT 1 FROM attackers a WHERE a.alliance_id = alliances.alliance_id)
+            AND NOT EXISTS (SELECT 1 FROM victims v WHERE v.alliance_id = alliances.alliance_id)
+            AND NOT EXISTS (SELECT 1 FROM character_public_info c WHERE c.alliance_id = alliances.alliance_id)
+            AND NOT EXISTS (SELECT 1 FROM corporations co WHERE co.alliance_id = alliances.alliance_id)"#,
+        None,
+    )
+    .await
+}
+
+/// Apply a retention policy: prune old killmails, then clean up the characters,
+/// corporations and alliances they leave behind. Orphans are removed in that
+/// order so that corporations and alliances are no longer referenced by the
+/// time we get to them.
+pub async fn apply_policy(
+    db: &DatabaseConnection,
+    policy: &RetentionPolicy,
+    archive_path: Option<&Path>,
+) -> Result<PruneReport, RetentionError> {
+    let mut report = PruneReport::default();
+    if let Some(days) = policy.killmail_days {
+        report.killmails = prune_killmails(db, days, archive_path).await?;
+    }
+    if let Some(days) = policy.character_days {
+        report.characters = prune_orphaned_characters(db, days).await?;
+        info!("Pruned {} orphaned characters", report.characters);
+    }
+    if let Some(days) = policy.market_price_days {
+        report.market_prices = prune_market_prices(db, days).await?;
+        info!("Pruned {} market prices", report.market_prices);
+    }
+    if policy.prune_orphaned_organizations {
+        report.corporations = prune_orphaned_corporations(db).await?;
+        info!("Pruned {} orphaned corporations", report.corporations);
+        report.alliances = prune_orphaned_alliances(db).await?;
+        info!("Pruned {} orphaned alliances", report.alliances);
+    }
+    Ok(report)
+}
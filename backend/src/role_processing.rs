@@ -0,0 +1,182 @@
+use crate::config::StatsWindow;
+use crate::stats_processing;
+use crate::stats_processing::push_window_filter;
+use chrono::NaiveDateTime;
+use schemars::JsonSchema;
+use sea_orm::{DatabaseConnection, DbBackend, DbErr, FromQueryResult, Statement, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Something worth knowing about a pilot before engaging them
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum RoleTag {
+    Cyno,
+    Tackle,
+    Logistics,
+    Capital,
+    CovertOps,
+    HotdropRisk,
+}
+
+/// Ship groups that mark a role. We only have hulls to go on, not fittings,
+/// so e.g. cyno alts are recognised by the ships that can fit a covert cyno.
+/// Strategic cruisers need a subsystem for it and aren't counted.
+const ROLE_GROUPS: &[(RoleTag, &[u64])] = &[
+    // Force Recon Ship, Black Ops, Covert Ops, Stealth Bomber, Blockade
+    // Runner, Expedition Frigate
+    (RoleTag::Cyno, &[833, 898, 830, 834, 1202, 1283]),
+    // Interdictor, Heavy Interdiction Cruiser, Interceptor
+    (RoleTag::Tackle, &[541, 894, 831]),
+    // Logistics, Logistics Frigate, Force Auxiliary
+    (RoleTag::Logistics, &[832, 1527, 1538]),
+    // Titan, Dreadnought, Carrier, Supercarrier, Capital Industrial Ship,
+    // Force Auxiliary, Lancer Dreadnought
+    (RoleTag::Capital, &[30, 485, 547, 659, 883, 1538, 4594]),
+    // Covert Ops, Stealth Bomber, Blockade Runner
+    (RoleTag::CovertOps, &[830, 834, 1202]),
+    // Black Ops
+    (RoleTag::HotdropRisk, &[898]),
+];
+
+/// A role and the killmails it was inferred from
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct RoleEvidence {
+    pub role: RoleTag,
+    /// Kills the pilot was on in a ship of this role
+    pub flown: usize,
+    /// Ships of this role the pilot lost
+    pub lost: usize,
+    pub last_seen: NaiveDateTime,
+}
+
+/// The roles a ship group marks, if any
+pub fn get_roles(group_id: u64) -> impl Iterator<Item = RoleTag> {
+    ROLE_GROUPS
+        .iter()
+        .filter(move |(_, group_ids)| group_ids.contains(&group_id))
+        .map(|(role, _)| *role)
+}
+
+#[derive(Debug, FromQueryResult)]
+struct RoleGroupCount {
+    character_id: u64,
+    is_loss: i32,
+    group_id: u64,
+    count: i64,
+    last_seen: NaiveDateTime,
+}
+
+/// Tag each character with roles from the ships they flew on kills and lost
+/// inside `window`, using only killmails and type data already in the
+/// database. Roles are ordered by how much evidence there is for them.
+pub async fn get_character_roles(
+    db: &DatabaseConnection,
+    character_ids: &[u64],
+    window: &StatsWindow,
+) -> Result<HashMap<u64, Vec<RoleEvidence>>, DbErr> {
+    if character_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let mut group_ids: Vec<u64> = ROLE_GROUPS
+        .iter()
+        .flat_map(|(_, group_ids)| group_ids.iter().copied())
+        .collect();
+    group_ids.sort_unstable();
+    group_ids.dedup();
+    let mut values: Vec<Value> = Vec::new();
+    let mut sql = format!(
+        r#"SELECT c.character_id AS character_id, c.is_loss AS is_loss, t.group_id AS group_id,
+            COUNT(*) AS count, MAX(k.killmail_time) AS last_seen
+        FROM (
+            SELECT character_id, killmail_id, ship_type_id, 0 AS is_loss FROM attackers
+                WHERE character_id IN ({attacker_ids})
+            UNION ALL
+            SELECT character_id, killmail_id, ship_type_id, 1 AS is_loss FROM victims
+                WHERE character_id IN ({victim_ids})
+        ) c
+        JOIN killmails k ON k.killmail_id = c.killmail_id
+        JOIN esi_types t ON t.type_id = c.ship_type_id
+        WHERE t.group_id IN ({group_ids})"#,
+        attacker_ids = stats_processing::push_id_list(&mut values, character_ids),
+        victim_ids = stats_processing::push_id_list(&mut values, character_ids),
+        group_ids = stats_processing::push_id_list(&mut values, &group_ids),
+    );
+    push_window_filter(&mut sql, &mut values, window);
+    sql.push_str(" GROUP BY c.character_id, c.is_loss, t.group_id");
+    let mut evidence: HashMap<u64, HashMap<RoleTag, RoleEvidence>> = HashMap::new();
+    for count in RoleGroupCount::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::MySql,
+        &sql,
+        values,
+    ))
+    .all(db)
+    .await?
+    {
+        let character_evidence = evidence.entry(count.character_id).or_default();
+        for role in get_roles(count.group_id) {
+            let entry = character_evidence.entry(role).or_insert(RoleEvidence {
+                role,
+                flown: 0,
+                lost: 0,
+                last_seen: count.last_seen,
+            });
+            if count.is_loss == 1 {
+                entry.lost += count.count as usize;
+            } else {
+                entry.flown += count.count as usize;
+            }
+            if count.last_seen > entry.last_seen {
+                entry.last_seen = count.last_seen;
+            }
+        }
+    }
+    Ok(character_ids
+        .iter()
+        .map(|character_id| {
+            let mut roles: Vec<RoleEvidence> = evidence
+                .remove(character_id)
+                .map(|evidence| evidence.into_values().collect())
+                .unwrap_or_default();
+            roles.sort_by(|a, b| {
+                (b.flown + b.lost)
+                    .cmp(&(a.flown + a.lost))
+                    .then(b.last_seen.cmp(&a.last_seen))
+            });
+            (*character_id, roles)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roles(group_id: u64) -> Vec<RoleTag> {
+        get_roles(group_id).collect()
+    }
+
+    #[test]
+    fn covert_cyno_hulls_are_cyno() {
+        assert_eq!(roles(833), vec![RoleTag::Cyno]);
+        assert_eq!(roles(1283), vec![RoleTag::Cyno]);
+        assert_eq!(roles(898), vec![RoleTag::Cyno, RoleTag::HotdropRisk]);
+        for group_id in [830, 834, 1202] {
+            assert_eq!(roles(group_id), vec![RoleTag::Cyno, RoleTag::CovertOps]);
+        }
+    }
+
+    #[test]
+    fn groups_can_mark_several_roles() {
+        assert_eq!(roles(1538), vec![RoleTag::Logistics, RoleTag::Capital]);
+        assert_eq!(roles(541), vec![RoleTag::Tackle]);
+        assert_eq!(roles(485), vec![RoleTag::Capital]);
+    }
+
+    #[test]
+    fn other_groups_mark_nothing() {
+        // Strategic Cruiser, Frigate
+        assert!(roles(963).is_empty());
+        assert!(roles(25).is_empty());
+    }
+}
@@ -0,0 +1,468 @@
+use crate::config;
+use schemars::JsonSchema;
+use sea_orm::{DatabaseConnection, DbBackend, DbErr, FromQueryResult, Statement, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+const DEFAULT_RESULT_COUNT: u64 = 10;
+const MAX_RESULT_COUNT: u64 = 50;
+const MIN_QUERY_LENGTH: usize = 2;
+/// Shorter queries only match prefixes, they'd share letter pairs with too
+/// many names
+const MIN_FUZZY_QUERY_LENGTH: usize = 3;
+/// How many rows each match query may return before ranking
+const CANDIDATE_LIMIT: u64 = 200;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchKind {
+    Character,
+    Corporation,
+    Alliance,
+    Type,
+}
+
+impl SearchKind {
+    fn parse(kind: &str) -> Option<SearchKind> {
+        match kind {
+            "character" => Some(SearchKind::Character),
+            "corporation" => Some(SearchKind::Corporation),
+            "alliance" => Some(SearchKind::Alliance),
+            "type" => Some(SearchKind::Type),
+            _ => None,
+        }
+    }
+
+    fn table(&self) -> SearchTable {
+        let (table, id_column, name_column, ticker_column) = match self {
+            SearchKind::Character => (
+                "character_public_info",
+                "character_id",
+                "character_name",
+                None,
+            ),
+            SearchKind::Corporation => ("corporations", "corporation_id", "name", Some("ticker")),
+            SearchKind::Alliance => ("alliances", "alliance_id", "name", Some("ticker")),
+            SearchKind::Type => ("esi_types", "type_id", "type_name", None),
+        };
+        SearchTable {
+            table,
+            id_column,
+            name_column,
+            ticker_column,
+        }
+    }
+}
+
+/// Where the names for a kind of search live
+struct SearchTable {
+    table: &'static str,
+    id_column: &'static str,
+    name_column: &'static str,
+    ticker_column: Option<&'static str>,
+}
+
+/// How well a result matched, best first
+#[derive(
+    Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, PartialOrd, Ord,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchQuality {
+    /// The name or ticker is the query
+    Exact,
+    /// The name or ticker starts with the query
+    Prefix,
+    /// The query appears somewhere in the name
+    Contains,
+    /// The name shares letter pairs with the query
+    Similar,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct SearchResult {
+    pub id: u64,
+    pub name: String,
+    pub ticker: Option<String>,
+    #[serde(rename = "match")]
+    pub match_quality: MatchQuality,
+    /// Killmails in the default window. For types, killmails the type was
+    /// flown on.
+    pub activity: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct SearchResults {
+    pub query: String,
+    pub kind: SearchKind,
+    /// Best match first, ties broken by activity
+    pub results: Vec<SearchResult>,
+}
+
+/// A checked search from query parameters
+#[derive(Debug, Clone)]
+pub struct SearchQuery {
+    pub query: String,
+    pub kind: SearchKind,
+    pub limit: u64,
+}
+
+impl SearchQuery {
+    /// `kind` defaults to characters
+    pub fn parse(
+        query: &str,
+        kind: Option<&str>,
+        limit: Option<u64>,
+    ) -> Result<SearchQuery, String> {
+        let query = query.trim();
+        if query.chars().count() < MIN_QUERY_LENGTH {
+            return Err(format!(
+                "Queries need at least {} characters",
+                MIN_QUERY_LENGTH
+            ));
+        }
+        let kind = match kind {
+            Some(kind) => SearchKind::parse(kind).ok_or_else(|| {
+                format!(
+                    "Unknown kind {}, expected character, corporation, alliance or type",
+                    kind
+                )
+            })?,
+            None => SearchKind::Character,
+        };
+        let limit = limit.unwrap_or(DEFAULT_RESULT_COUNT);
+        if limit == 0 || limit > MAX_RESULT_COUNT {
+            return Err(format!("limit must be between 1 and {}", MAX_RESULT_COUNT));
+        }
+        Ok(SearchQuery {
+            query: query.to_string(),
+            kind,
+            limit,
+        })
+    }
+}
+
+#[derive(Debug, FromQueryResult)]
+struct Candidate {
+    id: u64,
+    name: String,
+    ticker: Option<String>,
+    /// Only selected for characters, whose activity comes from the daily
+    /// rollup
+    activity: Option<i64>,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct Activity {
+    id: u64,
+    activity: i64,
+}
+
+/// Escape LIKE wildcards so they match literally
+fn escape_like(query: &str) -> String {
+    query
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// The start of a candidate query on `kind`'s table `t`. Characters also get
+/// their activity in the default window from the daily rollup, so they can
+/// be picked by it.
+fn select_candidates(kind: SearchKind, values: &mut Vec<Value>) -> String {
+    let SearchTable {
+        table,
+        id_column,
+        name_column,
+        ticker_column,
+    } = kind.table();
+    let activity = match kind {
+        SearchKind::Character => {
+            let mut activity = String::from(
+                r#"(SELECT CAST(COALESCE(SUM(d.kills + d.losses), 0) AS SIGNED)
+                FROM character_daily_stats d WHERE d.character_id = t.character_id"#,
+            );
+            if let Some(start_time) = config::get().stats.get_default_window().start_time() {
+                activity.push_str(" AND d.day >= ?");
+                values.push(start_time.date().into());
+            }
+            activity.push(')');
+            activity
+        }
+        _ => "NULL".to_string(),
+    };
+    format!(
+        r#"SELECT t.{id_column} AS id, t.{name_column} AS name, {ticker} AS ticker,
+            {activity} AS activity
+        FROM {table} t"#,
+        ticker = ticker_column
+            .map(|column| format!("t.{}", column))
+            .unwrap_or_else(|| "NULL".to_string()),
+    )
+}
+
+async fn get_candidates(
+    db: &DatabaseConnection,
+    sql: String,
+    values: Vec<Value>,
+) -> Result<Vec<Candidate>, DbErr> {
+    Candidate::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::MySql,
+        &sql,
+        values,
+    ))
+    .all(db)
+    .await
+}
+
+/// Names or tickers matching the LIKE `pattern` built from the query. The
+/// names use a case-insensitive collation. Characters keep the most active
+/// matches, everything else the exact match and then the shortest names.
+async fn get_like_matches(
+    db: &DatabaseConnection,
+    kind: SearchKind,
+    query: &str,
+    pattern: String,
+) -> Result<Vec<Candidate>, DbErr> {
+    let SearchTable {
+        name_column,
+        ticker_column,
+        ..
+    } = kind.table();
+    let mut values: Vec<Value> = Vec::new();
+    let mut sql = select_candidates(kind, &mut values);
+    sql.push_str(&format!(" WHERE t.{} LIKE ?", name_column));
+    values.push(pattern.clone().into());
+    if let Some(ticker_column) = ticker_column {
+        sql.push_str(&format!(" OR t.{} LIKE ?", ticker_column));
+        values.push(pattern.into());
+    }
+    match kind {
+        SearchKind::Character => sql.push_str(" ORDER BY activity DESC"),
+        _ => {
+            sql.push_str(&format!(" ORDER BY t.{} = ? DESC", name_column));
+            values.push(query.into());
+        }
+    }
+    sql.push_str(&format!(", CHAR_LENGTH(t.{}) LIMIT ?", name_column));
+    values.push(CANDIDATE_LIMIT.into());
+    get_candidates(db, sql, values).await
+}
+
+/// Names or tickers starting with the query. The names are indexed, so
+/// finding them is cheap.
+async fn get_prefix_matches(
+    db: &DatabaseConnection,
+    kind: SearchKind,
+    query: &str,
+) -> Result<Vec<Candidate>, DbErr> {
+    get_like_matches(db, kind, query, format!("{}%", escape_like(query))).await
+}
+
+/// Names or tickers containing the query, for databases without the ngram
+/// full text index. This scans the whole table.
+async fn get_contains_matches(
+    db: &DatabaseConnection,
+    kind: SearchKind,
+    query: &str,
+) -> Result<Vec<Candidate>, DbErr> {
+    get_like_matches(db, kind, query, format!("%{}%", escape_like(query))).await
+}
+
+/// Names sharing letter pairs with the query, most alike first. This goes
+/// through the ngram full text index, so it works for names in any language
+/// and finds both names containing the query and misspellings of it. The
+/// index needs MySQL's ngram parser, see the migration adding it.
+async fn get_fuzzy_matches(
+    db: &DatabaseConnection,
+    kind: SearchKind,
+    query: &str,
+) -> Result<Vec<Candidate>, DbErr> {
+    let name_column = kind.table().name_column;
+    let mut values: Vec<Value> = Vec::new();
+    let mut sql = select_candidates(kind, &mut values);
+    sql.push_str(&format!(
+        r#" WHERE MATCH(t.{name_column}) AGAINST (?)
+        ORDER BY MATCH(t.{name_column}) AGAINST (?) DESC, CHAR_LENGTH(t.{name_column})
+        LIMIT ?"#,
+    ));
+    values.push(query.into());
+    values.push(query.into());
+    values.push(CANDIDATE_LIMIT.into());
+    get_candidates(db, sql, values).await
+}
+
+/// Killmails each organization or type was on in the default window.
+/// Characters already have theirs from `select_candidates`.
+async fn get_activity(
+    db: &DatabaseConnection,
+    kind: SearchKind,
+    ids: &[u64],
+) -> Result<HashMap<u64, u64>, DbErr> {
+    let column = match kind {
+        SearchKind::Character => return Ok(HashMap::new()),
+        SearchKind::Corporation => "corporation_id",
+        SearchKind::Alliance => "alliance_id",
+        SearchKind::Type => "ship_type_id",
+    };
+    if ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let window = config::get().stats.get_default_window();
+    let placeholders = vec!["?"; ids.len()].join(", ");
+    let mut values: Vec<Value> = Vec::new();
+    let mut time_filter = String::new();
+    if window.start_time().is_some() {
+        time_filter.push_str(" AND k.killmail_time >= ?");
+    }
+    let mut sql = String::from(
+        "SELECT s.id, CAST(COUNT(DISTINCT s.killmail_id) AS SIGNED) AS activity FROM (",
+    );
+    let mut parts: Vec<String> = Vec::new();
+    for table in ["attackers", "victims"] {
+        parts.push(format!(
+            r#"SELECT p.{column} AS id, p.killmail_id FROM {table} p
+            JOIN killmails k ON k.killmail_id = p.killmail_id
+            WHERE p.{column} IN ({placeholders}){time_filter}"#,
+        ));
+        values.extend(ids.iter().map(|id| Value::from(*id)));
+        if let Some(start_time) = window.start_time() {
+            values.push(start_time.into());
+        }
+    }
+    sql.push_str(&parts.join(" UNION ALL "));
+    sql.push_str(") s GROUP BY s.id");
+    Ok(Activity::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::MySql,
+        &sql,
+        values,
+    ))
+    .all(db)
+    .await?
+    .into_iter()
+    .map(|activity| (activity.id, activity.activity as u64))
+    .collect())
+}
+
+fn match_quality(candidate: &Candidate, query: &str) -> MatchQuality {
+    let query = query.to_lowercase();
+    let name = candidate.name.to_lowercase();
+    let ticker = candidate
+        .ticker
+        .as_ref()
+        .map(|ticker| ticker.to_lowercase());
+    if name == query || ticker.as_deref() == Some(query.as_str()) {
+        MatchQuality::Exact
+    } else if name.starts_with(&query) || ticker.is_some_and(|ticker| ticker.starts_with(&query)) {
+        MatchQuality::Prefix
+    } else if name.contains(&query) {
+        MatchQuality::Contains
+    } else {
+        MatchQuality::Similar
+    }
+}
+
+/// Find characters, organizations or types by partial or misspelled name.
+/// Names containing the query or misspelling it are only looked for when
+/// prefixes didn't find enough. Without the full text index only names
+/// containing the query are found.
+pub async fn search(db: &DatabaseConnection, query: &SearchQuery) -> Result<SearchResults, DbErr> {
+    let mut candidates = get_prefix_matches(db, query.kind, &query.query).await?;
+    if (candidates.len() as u64) < query.limit
+        && query.query.chars().count() >= MIN_FUZZY_QUERY_LENGTH
+    {
+        let mut seen: HashSet<u64> = candidates.iter().map(|candidate| candidate.id).collect();
+        let fuzzy = match get_fuzzy_matches(db, query.kind, &query.query).await {
+            Ok(fuzzy) => fuzzy,
+            Err(err) => {
+                warn!(
+                    "Full text name search failed, matching names containing the query instead: {:?}",
+                    err
+                );
+                get_contains_matches(db, query.kind, &query.query).await?
+            }
+        };
+        for candidate in fuzzy {
+            if seen.insert(candidate.id) {
+                candidates.push(candidate);
+            }
+        }
+    }
+    let ids: Vec<u64> = candidates.iter().map(|candidate| candidate.id).collect();
+    let activity = get_activity(db, query.kind, &ids).await?;
+    let mut results: Vec<SearchResult> = candidates
+        .into_iter()
+        .map(|candidate| SearchResult {
+            id: candidate.id,
+            match_quality: match_quality(&candidate, &query.query),
+            activity: candidate
+                .activity
+                .map(|activity| activity as u64)
+                .or_else(|| activity.get(&candidate.id).copied())
+                .unwrap_or(0),
+            name: candidate.name,
+            ticker: candidate.ticker,
+        })
+        .collect();
+    results.sort_by(|a, b| {
+        a.match_quality
+            .cmp(&b.match_quality)
+            .then(b.activity.cmp(&a.activity))
+            .then(a.name.len().cmp(&b.name.len()))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    results.truncate(query.limit as usize);
+    Ok(SearchResults {
+        query: query.query.clone(),
+        kind: query.kind,
+        results,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(name: &str, ticker: Option<&str>) -> Candidate {
+        Candidate {
+            id: 1,
+            name: name.to_string(),
+            ticker: ticker.map(str::to_string),
+            activity: None,
+        }
+    }
+
+    #[test]
+    fn exact_matches_ignore_case() {
+        let goons = candidate("Goonswarm Federation", Some("CONDI"));
+        assert_eq!(
+            match_quality(&goons, "goonswarm federation"),
+            MatchQuality::Exact
+        );
+        assert_eq!(match_quality(&goons, "condi"), MatchQuality::Exact);
+    }
+
+    #[test]
+    fn prefixes_of_name_or_ticker() {
+        let goons = candidate("Goonswarm Federation", Some("CONDI"));
+        assert_eq!(match_quality(&goons, "Goon"), MatchQuality::Prefix);
+        assert_eq!(match_quality(&goons, "CON"), MatchQuality::Prefix);
+    }
+
+    #[test]
+    fn contains_only_looks_at_the_name() {
+        let goons = candidate("Goonswarm Federation", Some("CONDI"));
+        assert_eq!(match_quality(&goons, "federation"), MatchQuality::Contains);
+        assert_eq!(match_quality(&goons, "ondi"), MatchQuality::Similar);
+    }
+
+    #[test]
+    fn misspellings_are_similar() {
+        let pilot = candidate("Ralph King-Griffin", None);
+        assert_eq!(match_quality(&pilot, "Ralf King"), MatchQuality::Similar);
+    }
+
+    #[test]
+    fn like_wildcards_are_escaped() {
+        assert_eq!(escape_like("100%_sure\\"), "100\\%\\_sure\\\\");
+    }
+}
@@ -0,0 +1,162 @@
+use crate::config::StatsWindow;
+use crate::entity::prelude::*;
+use crate::entity::*;
+use crate::stats_processing;
+use crate::stats_processing::push_window_filter;
+use chrono::NaiveDateTime;
+use schemars::JsonSchema;
+use sea_orm::prelude::*;
+use sea_orm::{DatabaseConnection, DbBackend, DbErr, FromQueryResult, Statement, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How often a ship or weapon type shows up, with its name and group
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct TypeUsage {
+    pub type_id: u64,
+    pub type_name: Option<String>,
+    pub group_id: Option<u64>,
+    pub group_name: Option<String>,
+    pub count: usize,
+    pub last_used: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+pub struct ShipProfile {
+    pub ships_flown: Vec<TypeUsage>,
+    pub ships_lost: Vec<TypeUsage>,
+    pub weapons_used: Vec<TypeUsage>,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct CharacterTypeCount {
+    character_id: u64,
+    type_id: u64,
+    count: i64,
+    last_used: NaiveDateTime,
+}
+
+/// Names and groups for a set of types, keyed by type id
+pub async fn get_type_info(
+    db: &DatabaseConnection,
+    type_ids: Vec<u64>,
+) -> Result<HashMap<u64, (esi_types::Model, Option<esi_groups::Model>)>, DbErr> {
+    if type_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    Ok(EsiTypes::find()
+        .filter(esi_types::Column::TypeId.is_in(type_ids))
+        .find_also_related(EsiGroups)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|(esi_type, group)| (esi_type.type_id, (esi_type, group)))
+        .collect())
+}
+
+pub fn to_type_usage(
+    counts: Vec<(u64, usize, NaiveDateTime)>,
+    type_info: &HashMap<u64, (esi_types::Model, Option<esi_groups::Model>)>,
+) -> Vec<TypeUsage> {
+    counts
+        .into_iter()
+        .map(|(type_id, count, last_used)| {
+            let info = type_info.get(&type_id);
+            TypeUsage {
+                type_id,
+                type_name: info.map(|(esi_type, _)| esi_type.type_name.clone()),
+                group_id: info.map(|(esi_type, _)| esi_type.group_id),
+                group_name: info
+                    .and_then(|(_, group)| group.as_ref())
+                    .map(|group| group.group_name.clone()),
+                count,
+                last_used,
+            }
+        })
+        .collect()
+}
+
+/// The `top_entries` types each character used most in `column` of `table`,
+/// most recently used first on ties
+async fn get_top_types(
+    db: &DatabaseConnection,
+    character_ids: &[u64],
+    window: &StatsWindow,
+    table: &str,
+    column: &str,
+) -> Result<HashMap<u64, Vec<(u64, usize, NaiveDateTime)>>, DbErr> {
+    let mut values: Vec<Value> = Vec::new();
+    let mut sql = format!(
+        r#"SELECT p.character_id AS character_id, p.{column} AS type_id, COUNT(*) AS count,
+            MAX(k.killmail_time) AS last_used
+        FROM {table} p
+        JOIN killmails k ON k.killmail_id = p.killmail_id
+        WHERE p.character_id IN ({ids}) AND p.{column} IS NOT NULL"#,
+        ids = stats_processing::push_id_list(&mut values, character_ids),
+    );
+    push_window_filter(&mut sql, &mut values, window);
+    sql.push_str(&format!(" GROUP BY p.character_id, p.{}", column));
+    let sql =
+        stats_processing::top_per_character(&sql, "g.count DESC, g.last_used DESC", &mut values);
+    let mut top_types: HashMap<u64, Vec<(u64, usize, NaiveDateTime)>> = HashMap::new();
+    for count in CharacterTypeCount::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::MySql,
+        &sql,
+        values,
+    ))
+    .all(db)
+    .await?
+    {
+        top_types.entry(count.character_id).or_default().push((
+            count.type_id,
+            count.count as usize,
+            count.last_used,
+        ));
+    }
+    Ok(top_types)
+}
+
+/// Work out which ships each character flies on kills, loses, and which
+/// weapons they use inside `window`
+pub async fn get_ship_profiles(
+    db: &DatabaseConnection,
+    character_ids: &[u64],
+    window: &StatsWindow,
+) -> Result<HashMap<u64, ShipProfile>, DbErr> {
+    if character_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let mut flown = get_top_types(db, character_ids, window, "attackers", "ship_type_id").await?;
+    let mut lost = get_top_types(db, character_ids, window, "victims", "ship_type_id").await?;
+    let mut weapons =
+        get_top_types(db, character_ids, window, "attackers", "weapon_type_id").await?;
+    let mut type_ids: Vec<u64> = flown
+        .values()
+        .chain(lost.values())
+        .chain(weapons.values())
+        .flatten()
+        .map(|(type_id, _, _)| *type_id)
+        .collect();
+    type_ids.sort_unstable();
+    type_ids.dedup();
+    let type_info = get_type_info(db, type_ids).await?;
+    Ok(character_ids
+        .iter()
+        .map(|character_id| {
+            let usage = |top_types: &mut HashMap<u64, Vec<(u64, usize, NaiveDateTime)>>| {
+                to_type_usage(
+                    top_types.remove(character_id).unwrap_or_default(),
+                    &type_info,
+                )
+            };
+            (
+                *character_id,
+                ShipProfile {
+                    ships_flown: usage(&mut flown),
+                    ships_lost: usage(&mut lost),
+                    weapons_used: usage(&mut weapons),
+                },
+            )
+        })
+        .collect())
+}
@@ -1,23 +1,49 @@
+use crate::activity_processing;
+use crate::activity_processing::ActivityProfile;
+use crate::awox_processing;
+use crate::config;
+use crate::config::StatsWindow;
+use crate::damage_processing;
+use crate::damage_processing::DamageStats;
 use crate::entity::prelude::*;
 use crate::entity::*;
 use crate::esi;
+use crate::gang_processing;
+use crate::gang_processing::GangProfile;
 use crate::killmail_processing::ProcessingError;
+use crate::location_processing;
+use crate::location_processing::LocationProfile;
+use crate::name_processing;
+use crate::name_processing::NameLookup;
+use crate::role_processing;
+use crate::role_processing::RoleEvidence;
+use crate::ship_processing;
+use crate::ship_processing::ShipProfile;
+use crate::summary_processing;
+use crate::valuation_processing;
+use crate::valuation_processing::IskStats;
 use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use futures::{stream, StreamExt};
+use schemars::JsonSchema;
+use sea_orm::ActiveModelTrait;
 use sea_orm::ColumnTrait;
 use sea_orm::EntityTrait;
 use sea_orm::QueryFilter;
 use sea_orm::Set;
-use sea_orm::{DatabaseConnection, DbErr};
+use sea_orm::{DatabaseConnection, DbErr, Value};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::time::Instant;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+const KILLMAIL_CHUNK_SIZE: usize = 500;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
 pub struct KillLossRatio {
     pub kills: usize,
     pub losses: usize,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
 pub struct CharInfo {
     pub alliance_name: Option<String>,
     pub alliance_ticker: Option<String>,
@@ -25,163 +51,255 @@ pub struct CharInfo {
     pub corporation_ticker: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct WindowedStats {
+    pub window: String,
+    pub kill_loss_ratio: KillLossRatio,
+    pub solo_kill_loss_ratio: KillLossRatio,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
 pub struct CharacterStats {
     pub char_info: CharInfo,
+    /// The window `kill_loss_ratio` and `solo_kill_loss_ratio` cover
+    pub window: String,
     pub kill_loss_ratio: KillLossRatio,
     pub solo_kill_loss_ratio: KillLossRatio,
+    pub windows: Vec<WindowedStats>,
+    pub ship_profile: ShipProfile,
+    pub activity: ActivityProfile,
+    pub gang_profile: GangProfile,
+    pub roles: Vec<RoleEvidence>,
+    /// Kills of and losses to the pilot's own corporation or alliance
+    pub awox: KillLossRatio,
+    pub isk: IskStats,
+    pub damage: DamageStats,
+    pub locations: LocationProfile,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LookupStatus {
+    Found,
+    NotFound,
+    Error,
+}
+
+/// Stats for one name out of a bulk request
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct CharacterStatsResult {
+    pub name: String,
+    pub status: LookupStatus,
+    pub stats: Option<CharacterStats>,
+    pub error: Option<String>,
 }
 
-fn get_kill_loss_ratio(kills: &[StatsKillmail], losses: &[StatsKillmail]) -> KillLossRatio {
-    KillLossRatio {
-        kills: kills.len(),
-        losses: losses.len(),
+impl CharacterStatsResult {
+    pub fn found(name: String, stats: CharacterStats) -> CharacterStatsResult {
+        CharacterStatsResult {
+            name,
+            status: LookupStatus::Found,
+            stats: Some(stats),
+            error: None,
+        }
+    }
+
+    pub fn not_found(name: String) -> CharacterStatsResult {
+        CharacterStatsResult {
+            name,
+            status: LookupStatus::NotFound,
+            stats: None,
+            error: None,
+        }
+    }
+
+    pub fn error(name: String, error: String) -> CharacterStatsResult {
+        CharacterStatsResult {
+            name,
+            status: LookupStatus::Error,
+            stats: None,
+            error: Some(error),
+        }
     }
 }
 
-fn get_attacker_player_count(attackers: &[attackers::Model]) -> usize {
-    let attackers_clone: Vec<attackers::Model> = attackers.to_vec();
-    attackers_clone
-        .into_iter()
+pub fn get_attacker_player_count(attackers: &[attackers::Model]) -> usize {
+    attackers
+        .iter()
         .filter(|attacker| attacker.character_id.is_some())
         .count()
 }
 
-fn get_solo_kill_loss_ratio(kills: &[StatsKillmail], losses: &[StatsKillmail]) -> KillLossRatio {
-    let solo_kills = kills
-        .iter()
-        .filter(|kill| get_attacker_player_count(&kill.attackers) == 1)
-        .count();
-    let solo_losses = losses
-        .iter()
-        .filter(|loss| get_attacker_player_count(&loss.attackers) == 1)
-        .count();
-    KillLossRatio {
-        kills: solo_kills,
-        losses: solo_losses,
+pub struct StatsKillmail {
+    pub killmail_id: u64,
+    pub killmail_time: NaiveDateTime,
+    pub solar_system_id: u64,
+    pub awox: bool,
+    pub victim: victims::Model,
+    pub attackers: Vec<attackers::Model>,
+    pub position: Option<killmail_positions::Model>,
+}
+
+/// Restrict a query joined against `killmails k` to `window`
+pub fn push_window_filter(sql: &mut String, values: &mut Vec<Value>, window: &StatsWindow) {
+    if let Some(start_time) = window.start_time() {
+        sql.push_str(" AND k.killmail_time >= ?");
+        values.push(start_time.into());
     }
 }
 
-struct StatsKillmail {
-    killmail_id: u64,
-    killmail_time: NaiveDateTime,
-    solar_system_id: u64,
-    victim: victims::Model,
-    attackers: Vec<attackers::Model>,
-    position: killmail_positions::Model,
+/// Placeholders for an `IN (...)` list of `ids`, pushing the ids onto `values`
+pub fn push_id_list(values: &mut Vec<Value>, ids: &[u64]) -> String {
+    values.extend(ids.iter().map(|id| Value::from(*id)));
+    vec!["?"; ids.len()].join(", ")
 }
 
-async fn get_statskillmail_from_id(
-    db: &DatabaseConnection,
-    killmail_id: u64,
-) -> Result<Option<StatsKillmail>, DbErr> {
-    let attackers = Attackers::find()
-        .filter(attackers::Column::KillmailId.eq(killmail_id))
-        .all(db)
-        .await?;
-    let victim = Victims::find()
-        .filter(victims::Column::KillmailId.eq(killmail_id))
-        .one(db)
-        .await?;
-    if let Some((killmail, position)) = Killmails::find()
-        .filter(killmails::Column::KillmailId.eq(killmail_id))
-        .find_with_related(KillmailPositions)
-        .one(db)
-        .await?
-    {
-        Ok(Some(StatsKillmail {
-            killmail_id: killmail.killmail_id,
-            killmail_time: killmail.killmail_time,
-            solar_system_id: killmail.solar_system_id,
-            victim: victim.unwrap(),
-            attackers,
-            position: position.unwrap(),
-        }))
-    } else {
-        Ok(None)
-    }
+/// A subquery with a `character_id, killmail_id, is_loss` row for every
+/// killmail each of `character_ids` was on. Join it against `killmails k`.
+pub fn character_killmails_sql(values: &mut Vec<Value>, character_ids: &[u64]) -> String {
+    format!(
+        r#"SELECT character_id, killmail_id, 0 AS is_loss FROM attackers
+            WHERE character_id IN ({})
+        UNION
+        SELECT character_id, killmail_id, 1 AS is_loss FROM victims
+            WHERE character_id IN ({})"#,
+        push_id_list(values, character_ids),
+        push_id_list(values, character_ids)
+    )
 }
 
-async fn get_kills_from_list(
-    db: &DatabaseConnection,
-    kills: Vec<u64>,
-) -> Result<Vec<StatsKillmail>, DbErr> {
-    let mut results: Vec<StatsKillmail> = Vec::new();
-    let mut err: Option<DbErr> = Option::None;
-    for attacker_id in kills {
-        let killmail_stats_res = get_statskillmail_from_id(&db, attacker_id).await;
-        match killmail_stats_res {
-            Ok(opt) => {
-                if let Some(skm) = opt {
-                    results.push(skm);
-                }
-            }
-            Err(e) => {
-                err = Some(e);
-            }
-        }
-    }
-    if let Some(e) = err {
-        Err(e)
-    } else {
-        Ok(results)
-    }
+/// Keep the first `stats.top_entries` rows per character of a query grouped
+/// by `character_id`, sorted by `order` on the grouped rows `g`
+pub fn top_per_character(sql: &str, order: &str, values: &mut Vec<Value>) -> String {
+    values.push((config::get().stats.top_entries as u64).into());
+    format!(
+        r#"SELECT * FROM (
+            SELECT g.*, ROW_NUMBER() OVER (PARTITION BY g.character_id ORDER BY {order})
+                AS entry_number
+            FROM ({sql}) g
+        ) r
+        WHERE r.entry_number <= ?
+        ORDER BY r.character_id, r.entry_number"#
+    )
 }
 
-async fn get_character_kills(
+async fn get_killmail_chunk(
     db: &DatabaseConnection,
-    char_id: u64,
+    killmail_ids: &[u64],
 ) -> Result<Vec<StatsKillmail>, DbErr> {
-    let attacker_list = Attackers::find()
-        .filter(attackers::Column::CharacterId.eq(char_id))
+    let killmails = Killmails::find()
+        .filter(killmails::Column::KillmailId.is_in(killmail_ids.to_vec()))
         .all(db)
         .await?;
-    let killmail_ids = attacker_list
+    let mut victims: HashMap<u64, victims::Model> = Victims::find()
+        .filter(victims::Column::KillmailId.is_in(killmail_ids.to_vec()))
+        .all(db)
+        .await?
         .into_iter()
-        .map(|attacker| attacker.killmail_id)
+        .map(|victim| (victim.killmail_id, victim))
         .collect();
-    get_kills_from_list(db, killmail_ids).await
-}
-
-async fn get_character_losses(
-    db: &DatabaseConnection,
-    char_id: u64,
-) -> Result<Vec<StatsKillmail>, DbErr> {
-    let victim_list = Victims::find()
-        .filter(victims::Column::CharacterId.eq(char_id))
+    let mut positions: HashMap<u64, killmail_positions::Model> = KillmailPositions::find()
+        .filter(killmail_positions::Column::KillmailId.is_in(killmail_ids.to_vec()))
         .all(db)
-        .await?;
-    let killmail_ids = victim_list
+        .await?
         .into_iter()
-        .map(|victim| victim.killmail_id)
+        .map(|position| (position.killmail_id, position))
         .collect();
-    get_kills_from_list(db, killmail_ids).await
+    let mut attackers: HashMap<u64, Vec<attackers::Model>> = HashMap::new();
+    for attacker in Attackers::find()
+        .filter(attackers::Column::KillmailId.is_in(killmail_ids.to_vec()))
+        .all(db)
+        .await?
+    {
+        attackers
+            .entry(attacker.killmail_id)
+            .or_default()
+            .push(attacker);
+    }
+    let mut results: Vec<StatsKillmail> = Vec::with_capacity(killmails.len());
+    for killmail in killmails {
+        match victims.remove(&killmail.killmail_id) {
+            Some(victim) => results.push(StatsKillmail {
+                killmail_id: killmail.killmail_id,
+                killmail_time: killmail.killmail_time,
+                solar_system_id: killmail.solar_system_id,
+                awox: killmail.awox,
+                victim,
+                attackers: attackers.remove(&killmail.killmail_id).unwrap_or_default(),
+                position: positions.remove(&killmail.killmail_id),
+            }),
+            None => warn!(
+                "Killmail {} has no victim, leaving it out of stats",
+                killmail.killmail_id
+            ),
+        }
+    }
+    Ok(results)
 }
 
-async fn get_character_alliance(
+/// Load killmails with their victims, attackers and positions, four queries
+/// per chunk of `KILLMAIL_CHUNK_SIZE` killmails
+pub async fn get_kills_from_list(
     db: &DatabaseConnection,
-    character_info: &character_public_info::Model,
-) -> Result<Option<alliances::Model>, DbErr> {
-    if let Some(alliance_id) = character_info.alliance_id {
-        let alliance = Alliances::find()
-            .filter(alliances::Column::AllianceId.eq(alliance_id))
-            .one(db)
-            .await?;
-        Ok(alliance)
-    } else {
-        Ok(None)
+    mut killmail_ids: Vec<u64>,
+) -> Result<Vec<StatsKillmail>, DbErr> {
+    killmail_ids.sort_unstable();
+    killmail_ids.dedup();
+    let mut results: Vec<StatsKillmail> = Vec::with_capacity(killmail_ids.len());
+    for chunk in killmail_ids.chunks(KILLMAIL_CHUNK_SIZE) {
+        results.append(&mut get_killmail_chunk(db, chunk).await?);
     }
+    Ok(results)
 }
 
-async fn get_character_corporation(
+/// Corporation and alliance names for each character
+async fn get_char_infos(
     db: &DatabaseConnection,
-    character_info: &character_public_info::Model,
-) -> Result<Option<corporations::Model>, DbErr> {
-    let corporation = Corporations::find()
-        .filter(corporations::Column::CorporationId.eq(character_info.corporation_id))
-        .one(db)
-        .await?;
-    Ok(corporation)
+    characters: &[character_public_info::Model],
+) -> Result<HashMap<u64, CharInfo>, DbErr> {
+    let corporation_ids: Vec<u64> = characters
+        .iter()
+        .map(|character| character.corporation_id)
+        .collect();
+    let alliance_ids: Vec<u64> = characters
+        .iter()
+        .filter_map(|character| character.alliance_id)
+        .collect();
+    let corporations: HashMap<u64, corporations::Model> = if corporation_ids.is_empty() {
+        HashMap::new()
+    } else {
+        Corporations::find()
+            .filter(corporations::Column::CorporationId.is_in(corporation_ids))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|corporation| (corporation.corporation_id, corporation))
+            .collect()
+    };
+    let alliances: HashMap<u64, alliances::Model> = if alliance_ids.is_empty() {
+        HashMap::new()
+    } else {
+        Alliances::find()
+            .filter(alliances::Column::AllianceId.is_in(alliance_ids))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|alliance| (alliance.alliance_id, alliance))
+            .collect()
+    };
+    Ok(characters
+        .iter()
+        .map(|character| {
+            let alliance = character
+                .alliance_id
+                .and_then(|alliance_id| alliances.get(&alliance_id).cloned());
+            let corporation = corporations.get(&character.corporation_id).cloned();
+            (
+                character.character_id,
+                get_char_info(&alliance, &corporation),
+            )
+        })
+        .collect())
 }
 
 fn get_char_info(
@@ -217,72 +335,295 @@ fn get_char_info(
 pub async fn update_character_public_info(
     db: &DatabaseConnection,
     character: character_public_info::Model,
-) -> Result<Option<character_public_info::Model>, ProcessingError> {
-    let char_id = character.character_id.clone();
-    // let mut active_char: character_public_info::ActiveModel = character.into();
-    let mut new_active_model = esi::get_character(char_id.clone()).await?;
-    // let new_active_model = character_public_info::ActiveModel::from_esi(char_id.clone(), new_char_info);
+) -> Result<character_public_info::Model, ProcessingError> {
+    let char_id = character.character_id;
+    let mut new_active_model = esi::get_character(char_id).await?;
     new_active_model.last_updated = Set(Some(DateTime::naive_utc(&Utc::now())));
-    let new_info_result = CharacterPublicInfo::find()
-        .filter(character_public_info::Column::CharacterId.eq(char_id))
-        .one(db)
-        .await?;
-    Ok(new_info_result)
+    let new_info = new_active_model.update(db).await?;
+    name_processing::record_character_name(db, char_id, &new_info.character_name).await?;
+    Ok(new_info)
+}
+
+/// Public info is refreshed from ESI once it's a few days old
+fn is_stale(character: &character_public_info::Model) -> bool {
+    match character.last_updated {
+        Some(last_updated) => Utc::now().naive_utc() - last_updated > Duration::days(3),
+        None => true,
+    }
+}
+
+/// Refresh a character's stale public info. The character may have been
+/// renamed since we last saw it, in which case `name` no longer belongs to it.
+async fn refresh_character_public_info(
+    db: &DatabaseConnection,
+    name: &str,
+    character: character_public_info::Model,
+) -> Result<Option<character_public_info::Model>, ProcessingError> {
+    info!("Info for character {} out of date, updating", name);
+    let new_info = update_character_public_info(db, character).await?;
+    if new_info.character_name.to_lowercase() == name.to_lowercase() {
+        Ok(Some(new_info))
+    } else {
+        info!(
+            "Character {} is now known as {}",
+            name, new_info.character_name
+        );
+        Ok(None)
+    }
 }
 
 pub async fn get_or_update_character_public_info(
     db: &DatabaseConnection,
     name: String,
 ) -> Result<Option<character_public_info::Model>, ProcessingError> {
+    let character_id = match name_processing::lookup_character_name(db, &name).await? {
+        NameLookup::Found(character_id) => character_id,
+        NameLookup::NotFound => return Ok(None),
+        NameLookup::Ambiguous(character_ids) => {
+            warn!("Name {} is held by characters {:?}", name, character_ids);
+            return Err(ProcessingError::AmbiguousCharacterName(character_ids));
+        }
+    };
     let character_info_result = CharacterPublicInfo::find()
-        .filter(character_public_info::Column::CharacterName.eq(name.clone()))
+        .filter(character_public_info::Column::CharacterId.eq(character_id))
         .one(db)
         .await?;
     // If the public info hasn't been updated in the last few days, update it
-    if let Some(character) = character_info_result {
-        if let Some(last_updated) = character.last_updated {
-            let now = Utc::now().naive_utc();
-            if now - last_updated > Duration::days(3) {
-                let new_info_result = update_character_public_info(db, character).await?;
-                info!("Info for character {} out of date, updating", name);
-                Ok(new_info_result)
-            } else {
-                Ok(Some(character))
-            }
-        } else {
-            let new_info_result = update_character_public_info(db, character).await?;
-            info!("Updated character public info for {}", name);
-            Ok(new_info_result)
+    match character_info_result {
+        Some(character) if is_stale(&character) => {
+            refresh_character_public_info(db, &name, character).await
+        }
+        character => Ok(character),
+    }
+}
+
+pub async fn get_character_activity(
+    db: &DatabaseConnection,
+    name: String,
+    window: &StatsWindow,
+) -> Result<Option<ActivityProfile>, ProcessingError> {
+    match get_or_update_character_public_info(db, name).await? {
+        Some(char_info) => {
+            let character_id = char_info.character_id;
+            let mut profiles =
+                activity_processing::get_character_activities(db, &[character_id], window).await?;
+            Ok(profiles.remove(&character_id))
+        }
+        None => Ok(None),
+    }
+}
+
+async fn get_windowed_stats(
+    db: &DatabaseConnection,
+    character_ids: &[u64],
+) -> Result<HashMap<u64, Vec<WindowedStats>>, DbErr> {
+    let mut windowed_stats: HashMap<u64, Vec<WindowedStats>> = HashMap::new();
+    for window in config::get().stats.windows.iter() {
+        for (character_id, summary) in
+            summary_processing::get_window_summaries(db, character_ids, window).await?
+        {
+            windowed_stats
+                .entry(character_id)
+                .or_default()
+                .push(WindowedStats {
+                    window: window.name.clone(),
+                    kill_loss_ratio: summary.kill_loss_ratio,
+                    solo_kill_loss_ratio: summary.solo_kill_loss_ratio,
+                });
         }
-    } else {
-        Ok(None)
     }
+    Ok(windowed_stats)
+}
+
+/// Stats for a set of characters inside `window`. Each profile is one grouped
+/// query over the whole set, so the number of queries doesn't grow with the
+/// number of characters or how many killmails they have.
+pub async fn get_stats_for_characters(
+    db: &DatabaseConnection,
+    characters: &[character_public_info::Model],
+    window: &StatsWindow,
+) -> Result<HashMap<u64, CharacterStats>, DbErr> {
+    let character_ids: Vec<u64> = characters
+        .iter()
+        .map(|character| character.character_id)
+        .collect();
+    let mut char_infos = get_char_infos(db, characters).await?;
+    let mut summaries =
+        summary_processing::get_window_summaries(db, &character_ids, window).await?;
+    let mut windows = get_windowed_stats(db, &character_ids).await?;
+    let mut ship_profiles = ship_processing::get_ship_profiles(db, &character_ids, window).await?;
+    let mut activities =
+        activity_processing::get_character_activities(db, &character_ids, window).await?;
+    let mut gang_profiles = gang_processing::get_gang_profiles(db, &character_ids, window).await?;
+    let mut roles = role_processing::get_character_roles(db, &character_ids, window).await?;
+    let mut awox = awox_processing::get_awox_counts(db, &character_ids, window).await?;
+    let mut isk = valuation_processing::get_isk_stats(db, &character_ids, window).await?;
+    let mut damage =
+        damage_processing::get_character_damage_stats(db, &character_ids, window).await?;
+    let mut locations =
+        location_processing::get_location_profiles(db, &character_ids, window).await?;
+    Ok(character_ids
+        .iter()
+        .map(|character_id| {
+            let summary = summaries.remove(character_id).unwrap_or_default();
+            (
+                *character_id,
+                CharacterStats {
+                    char_info: char_infos
+                        .remove(character_id)
+                        .unwrap_or_else(|| get_char_info(&None, &None)),
+                    window: window.name.clone(),
+                    kill_loss_ratio: summary.kill_loss_ratio,
+                    solo_kill_loss_ratio: summary.solo_kill_loss_ratio,
+                    windows: windows.remove(character_id).unwrap_or_default(),
+                    ship_profile: ship_profiles.remove(character_id).unwrap_or_default(),
+                    activity: activities.remove(character_id).unwrap_or_default(),
+                    gang_profile: gang_profiles.remove(character_id).unwrap_or_default(),
+                    roles: roles.remove(character_id).unwrap_or_default(),
+                    awox: awox.remove(character_id).unwrap_or_default(),
+                    isk: isk.remove(character_id).unwrap_or_default(),
+                    damage: damage.remove(character_id).unwrap_or_default(),
+                    locations: locations.remove(character_id).unwrap_or_default(),
+                },
+            )
+        })
+        .collect())
 }
 
 pub async fn get_character_stats(
     db: &DatabaseConnection,
     name: String,
+    window: &StatsWindow,
 ) -> Result<Option<CharacterStats>, ProcessingError> {
     let start_time = Instant::now();
-    let character_info_result = get_or_update_character_public_info(&db, name).await?;
+    let character_info_result = get_or_update_character_public_info(db, name).await?;
     match character_info_result {
         Some(char_info) => {
-            let alliance = get_character_alliance(&db, &char_info).await?;
-            let corporation = get_character_corporation(&db, &char_info).await?;
-            let character_info = get_char_info(&alliance, &corporation);
-            let kills = get_character_kills(&db, char_info.character_id).await?;
-            let losses = get_character_losses(&db, char_info.character_id).await?;
-            let kill_loss_ratio = get_kill_loss_ratio(&kills, &losses);
-            let solo_kill_loss_ratio = get_solo_kill_loss_ratio(&kills, &losses);
+            let character_id = char_info.character_id;
+            let mut stats = get_stats_for_characters(db, &[char_info], window).await?;
             let end_time = Instant::now();
             let duration = (end_time - start_time).as_millis();
             info!("Request took {}ms", duration);
-            Ok(Some(CharacterStats {
-                char_info: character_info,
-                kill_loss_ratio,
-                solo_kill_loss_ratio,
-            }))
+            Ok(stats.remove(&character_id))
         }
         None => Ok(None),
     }
 }
+
+/// Removes later names that resolved to a character already in
+/// `characters`, returning them as `(name, character_id)`
+fn take_duplicate_ids(characters: &mut Vec<(String, u64)>) -> Vec<(String, u64)> {
+    let mut seen: HashSet<u64> = HashSet::new();
+    let mut duplicates: Vec<(String, u64)> = Vec::new();
+    characters.retain(|(name, character_id)| {
+        let first = seen.insert(*character_id);
+        if !first {
+            duplicates.push((name.clone(), *character_id));
+        }
+        first
+    });
+    duplicates
+}
+
+/// Stats for many characters already resolved from names, as
+/// `(name, character_id)`. Public info is loaded in one query and only stale
+/// entries are refreshed from ESI, then the stats of the whole set are
+/// computed together with `get_stats_for_characters`. Names resolving to the
+/// same character share its result.
+pub async fn get_many_character_stats(
+    db: &DatabaseConnection,
+    mut characters: Vec<(String, u64)>,
+    window: &StatsWindow,
+) -> Result<Vec<CharacterStatsResult>, DbErr> {
+    let duplicates = take_duplicate_ids(&mut characters);
+    let first_names: HashMap<u64, String> = characters
+        .iter()
+        .map(|(name, character_id)| (*character_id, name.clone()))
+        .collect();
+    let character_ids: Vec<u64> = characters
+        .iter()
+        .map(|(_, character_id)| *character_id)
+        .collect();
+    let mut public_info: HashMap<u64, character_public_info::Model> = if character_ids.is_empty() {
+        HashMap::new()
+    } else {
+        CharacterPublicInfo::find()
+            .filter(character_public_info::Column::CharacterId.is_in(character_ids))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|character| (character.character_id, character))
+            .collect()
+    };
+    let mut results: Vec<CharacterStatsResult> = Vec::new();
+    let mut fresh: Vec<(String, character_public_info::Model)> = Vec::new();
+    let mut stale: Vec<(String, character_public_info::Model)> = Vec::new();
+    for (name, character_id) in characters {
+        match public_info.remove(&character_id) {
+            Some(character) if is_stale(&character) => stale.push((name, character)),
+            Some(character) => fresh.push((name, character)),
+            None => results.push(CharacterStatsResult::not_found(name)),
+        }
+    }
+    let mut refreshes = stream::iter(stale)
+        .map(|(name, character)| async move {
+            let result = refresh_character_public_info(db, &name, character).await;
+            (name, result)
+        })
+        .buffer_unordered(config::get().concurrency.bulk_stats);
+    while let Some((name, result)) = refreshes.next().await {
+        match result {
+            Ok(Some(character)) => fresh.push((name, character)),
+            Ok(None) => results.push(CharacterStatsResult::not_found(name)),
+            Err(e) => {
+                error!("Failed to update character {}: {:?}", name, e);
+                results.push(CharacterStatsResult::error(
+                    name,
+                    "Failed to update character".to_string(),
+                ));
+            }
+        }
+    }
+    let characters: Vec<character_public_info::Model> = fresh
+        .iter()
+        .map(|(_, character)| character.clone())
+        .collect();
+    let mut stats = get_stats_for_characters(db, &characters, window).await?;
+    for (name, character) in fresh {
+        match stats.remove(&character.character_id) {
+            Some(stats) => results.push(CharacterStatsResult::found(name, stats)),
+            None => results.push(CharacterStatsResult::not_found(name)),
+        }
+    }
+    for (name, character_id) in duplicates {
+        let first_name = &first_names[&character_id];
+        if let Some(result) = results.iter().find(|result| &result.name == first_name) {
+            let result = CharacterStatsResult {
+                name,
+                ..result.clone()
+            };
+            results.push(result);
+        }
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn later_names_for_a_character_are_duplicates() {
+        let mut characters = vec![
+            ("Old Name".to_string(), 1),
+            ("Other".to_string(), 2),
+            ("New Name".to_string(), 1),
+        ];
+        let duplicates = take_duplicate_ids(&mut characters);
+        assert_eq!(
+            characters,
+            vec![("Old Name".to_string(), 1), ("Other".to_string(), 2)]
+        );
+        assert_eq!(duplicates, vec![("New Name".to_string(), 1)]);
+    }
+}
@@ -0,0 +1,306 @@
+use crate::config::StatsWindow;
+use crate::stats_processing;
+use crate::stats_processing::KillLossRatio;
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use datamodels::esi_models::ESIKillmail;
+use sea_orm::{
+    ConnectionTrait, DatabaseConnection, DatabaseTransaction, DbBackend, DbErr, FromQueryResult,
+    Statement, TransactionTrait, Value,
+};
+use std::collections::HashMap;
+
+#[derive(Debug, Default, Clone)]
+struct DailyStatsDelta {
+    kills: u64,
+    losses: u64,
+    solo_kills: u64,
+    solo_losses: u64,
+    final_blows: u64,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct SummaryTotals {
+    character_id: u64,
+    kills: i64,
+    losses: i64,
+    solo_kills: i64,
+    solo_losses: i64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CharacterSummary {
+    pub kill_loss_ratio: KillLossRatio,
+    pub solo_kill_loss_ratio: KillLossRatio,
+}
+
+fn get_killmail_deltas(killmail: &ESIKillmail) -> HashMap<u64, DailyStatsDelta> {
+    let mut deltas: HashMap<u64, DailyStatsDelta> = HashMap::new();
+    let player_count = killmail
+        .attackers
+        .iter()
+        .filter(|attacker| attacker.character_id.is_some())
+        .count();
+    let is_solo = player_count == 1;
+    for attacker in killmail.attackers.iter() {
+        if let Some(character_id) = attacker.character_id {
+            let delta = deltas.entry(character_id).or_default();
+            delta.kills = 1;
+            if is_solo {
+                delta.solo_kills = 1;
+            }
+            if attacker.final_blow {
+                delta.final_blows = 1;
+            }
+        }
+    }
+    if let Some(character_id) = killmail.victim.character_id {
+        let delta = deltas.entry(character_id).or_default();
+        delta.losses = 1;
+        if is_solo {
+            delta.solo_losses = 1;
+        }
+    }
+    deltas
+}
+
+async fn upsert_daily_stats(
+    txn: &DatabaseTransaction,
+    character_id: u64,
+    day: NaiveDate,
+    delta: DailyStatsDelta,
+) -> Result<(), DbErr> {
+    txn.execute(Statement::from_sql_and_values(
+        DbBackend::MySql,
+        r#"INSERT INTO character_daily_stats
+            (character_id, day, kills, losses, solo_kills, solo_losses, final_blows)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        ON DUPLICATE KEY UPDATE
+            kills = kills + VALUES(kills),
+            losses = losses + VALUES(losses),
+            solo_kills = solo_kills + VALUES(solo_kills),
+            solo_losses = solo_losses + VALUES(solo_losses),
+            final_blows = final_blows + VALUES(final_blows)"#,
+        vec![
+            character_id.into(),
+            day.into(),
+            delta.kills.into(),
+            delta.losses.into(),
+            delta.solo_kills.into(),
+            delta.solo_losses.into(),
+            delta.final_blows.into(),
+        ],
+    ))
+    .await?;
+    Ok(())
+}
+
+/// Add a newly stored killmail to the daily stats of every character on it.
+/// This must only be called once per killmail, the counters aren't idempotent,
+/// so it runs in the transaction that inserts the killmail.
+pub async fn record_killmail(
+    txn: &DatabaseTransaction,
+    killmail: &ESIKillmail,
+) -> Result<(), DbErr> {
+    let day = killmail.killmail_time.date();
+    for (character_id, delta) in get_killmail_deltas(killmail) {
+        upsert_daily_stats(txn, character_id, day, delta).await?;
+    }
+    Ok(())
+}
+
+/// Recompute the daily stats from the killmails currently in the database,
+/// either for every day or only for `day`. Clearing and recomputing happen in
+/// one transaction so killmails ingested meanwhile are counted exactly once.
+async fn rebuild_days(db: &DatabaseConnection, day: Option<NaiveDate>) -> Result<(), DbErr> {
+    let mut delete_sql = "DELETE FROM character_daily_stats".to_string();
+    let mut delete_values: Vec<Value> = Vec::new();
+    let mut time_filter = "";
+    let mut time_values: Vec<Value> = Vec::new();
+    if let Some(day) = day {
+        let start = day.and_hms(0, 0, 0);
+        delete_sql.push_str(" WHERE day = ?");
+        delete_values.push(day.into());
+        time_filter = " AND k.killmail_time >= ? AND k.killmail_time < ?";
+        time_values.push(start.into());
+        time_values.push((start + Duration::days(1)).into());
+    }
+    let txn = db.begin().await?;
+    info!("Clearing character daily stats");
+    txn.execute(Statement::from_sql_and_values(
+        DbBackend::MySql,
+        &delete_sql,
+        delete_values,
+    ))
+    .await?;
+    info!("Rebuilding kills");
+    txn.execute(Statement::from_sql_and_values(
+        DbBackend::MySql,
+        &format!(
+            r#"INSERT INTO character_daily_stats (character_id, day, kills, solo_kills, final_blows)
+            SELECT a.character_id, DATE(k.killmail_time), COUNT(DISTINCT a.killmail_id),
+                SUM(pc.players = 1), SUM(a.final_blow)
+            FROM attackers a
+            JOIN killmails k ON k.killmail_id = a.killmail_id
+            JOIN (
+                SELECT killmail_id, COUNT(character_id) AS players FROM attackers GROUP BY killmail_id
+            ) pc ON pc.killmail_id = a.killmail_id
+            WHERE a.character_id IS NOT NULL{}
+            GROUP BY a.character_id, DATE(k.killmail_time)"#,
+            time_filter
+        ),
+        time_values.clone(),
+    ))
+    .await?;
+    info!("Rebuilding losses");
+    txn.execute(Statement::from_sql_and_values(
+        DbBackend::MySql,
+        &format!(
+            r#"INSERT INTO character_daily_stats (character_id, day, losses, solo_losses)
+            SELECT v.character_id, DATE(k.killmail_time), COUNT(*), SUM(COALESCE(pc.players, 0) = 1)
+            FROM victims v
+            JOIN killmails k ON k.killmail_id = v.killmail_id
+            LEFT JOIN (
+                SELECT killmail_id, COUNT(character_id) AS players FROM attackers GROUP BY killmail_id
+            ) pc ON pc.killmail_id = v.killmail_id
+            WHERE v.character_id IS NOT NULL{}
+            GROUP BY v.character_id, DATE(k.killmail_time)
+            ON DUPLICATE KEY UPDATE losses = VALUES(losses), solo_losses = VALUES(solo_losses)"#,
+            time_filter
+        ),
+        time_values,
+    ))
+    .await?;
+    txn.commit().await
+}
+
+/// Throw away the daily stats tables and recompute them from the killmails
+/// currently in the database
+pub async fn rebuild(db: &DatabaseConnection) -> Result<(), DbErr> {
+    rebuild_days(db, None).await
+}
+
+/// Bring the daily stats in line after killmails before `cutoff` were pruned.
+/// Days before the cutoff are dropped and the day it falls on, which lost only
+/// some of its killmails, is recomputed.
+pub async fn forget_before(db: &DatabaseConnection, cutoff: NaiveDateTime) -> Result<(), DbErr> {
+    db.execute(Statement::from_sql_and_values(
+        DbBackend::MySql,
+        "DELETE FROM character_daily_stats WHERE day < ?",
+        vec![cutoff.date().into()],
+    ))
+    .await?;
+    rebuild_days(db, Some(cutoff.date())).await
+}
+
+/// Add summed up counts to each character's summary
+fn add_totals(summaries: &mut HashMap<u64, CharacterSummary>, totals: Vec<SummaryTotals>) {
+    for totals in totals {
+        let summary = summaries.entry(totals.character_id).or_default();
+        summary.kill_loss_ratio.kills += totals.kills as usize;
+        summary.kill_loss_ratio.losses += totals.losses as usize;
+        summary.solo_kill_loss_ratio.kills += totals.solo_kills as usize;
+        summary.solo_kill_loss_ratio.losses += totals.solo_losses as usize;
+    }
+}
+
+/// Sum each character's daily stats from `since` onwards, or over all time.
+/// Characters without any stats get an empty summary.
+pub async fn get_character_summaries(
+    db: &DatabaseConnection,
+    character_ids: &[u64],
+    since: Option<NaiveDate>,
+) -> Result<HashMap<u64, CharacterSummary>, DbErr> {
+    if character_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let mut values: Vec<Value> = Vec::new();
+    let mut sql = format!(
+        r#"SELECT character_id,
+            CAST(COALESCE(SUM(kills), 0) AS SIGNED) AS kills,
+            CAST(COALESCE(SUM(losses), 0) AS SIGNED) AS losses,
+            CAST(COALESCE(SUM(solo_kills), 0) AS SIGNED) AS solo_kills,
+            CAST(COALESCE(SUM(solo_losses), 0) AS SIGNED) AS solo_losses
+        FROM character_daily_stats
+        WHERE character_id IN ({})"#,
+        stats_processing::push_id_list(&mut values, character_ids)
+    );
+    if let Some(since) = since {
+        sql.push_str(" AND day >= ?");
+        values.push(since.into());
+    }
+    sql.push_str(" GROUP BY character_id");
+    let mut summaries: HashMap<u64, CharacterSummary> = character_ids
+        .iter()
+        .map(|character_id| (*character_id, CharacterSummary::default()))
+        .collect();
+    let totals = SummaryTotals::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::MySql,
+        &sql,
+        values,
+    ))
+    .all(db)
+    .await?;
+    add_totals(&mut summaries, totals);
+    Ok(summaries)
+}
+
+/// Count each character's killmails from `start_time` to the end of that
+/// day straight from the killmails, for the part of a window that doesn't
+/// cover a whole day of daily stats
+async fn get_partial_day_totals(
+    db: &DatabaseConnection,
+    character_ids: &[u64],
+    start_time: NaiveDateTime,
+) -> Result<Vec<SummaryTotals>, DbErr> {
+    let mut values: Vec<Value> = Vec::new();
+    let sql = format!(
+        r#"SELECT d.character_id AS character_id,
+            CAST(COALESCE(SUM(d.is_loss = 0), 0) AS SIGNED) AS kills,
+            CAST(COALESCE(SUM(d.is_loss = 1), 0) AS SIGNED) AS losses,
+            CAST(COALESCE(SUM(d.is_loss = 0 AND d.players = 1), 0) AS SIGNED) AS solo_kills,
+            CAST(COALESCE(SUM(d.is_loss = 1 AND d.players = 1), 0) AS SIGNED) AS solo_losses
+        FROM (
+            SELECT c.character_id, c.is_loss,
+                (SELECT COUNT(p.character_id) FROM attackers p WHERE p.killmail_id = c.killmail_id)
+                    AS players
+            FROM ({}) c
+            JOIN killmails k ON k.killmail_id = c.killmail_id
+            WHERE k.killmail_time >= ? AND k.killmail_time < ?
+        ) d
+        GROUP BY d.character_id"#,
+        stats_processing::character_killmails_sql(&mut values, character_ids)
+    );
+    let next_day = (start_time.date() + Duration::days(1)).and_hms(0, 0, 0);
+    values.push(start_time.into());
+    values.push(next_day.into());
+    SummaryTotals::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::MySql,
+        &sql,
+        values,
+    ))
+    .all(db)
+    .await
+}
+
+/// Each character's summary for `window`. Whole days come from the daily
+/// stats and the partial day the window starts on from the killmails, so the
+/// window starts exactly `hours` ago.
+pub async fn get_window_summaries(
+    db: &DatabaseConnection,
+    character_ids: &[u64],
+    window: &StatsWindow,
+) -> Result<HashMap<u64, CharacterSummary>, DbErr> {
+    match window.start_time() {
+        Some(start_time) => {
+            let first_whole_day = start_time.date() + Duration::days(1);
+            let mut summaries =
+                get_character_summaries(db, character_ids, Some(first_whole_day)).await?;
+            if !character_ids.is_empty() {
+                let totals = get_partial_day_totals(db, character_ids, start_time).await?;
+                add_totals(&mut summaries, totals);
+            }
+            Ok(summaries)
+        }
+        None => get_character_summaries(db, character_ids, None).await,
+    }
+}
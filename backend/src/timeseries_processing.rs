@@ -0,0 +1,295 @@
+use crate::config;
+use crate::organization_processing::OrganizationKind;
+use chrono::{Datelike, Duration, NaiveDate, Utc};
+use schemars::JsonSchema;
+use sea_orm::{DatabaseConnection, DbBackend, DbErr, FromQueryResult, Statement, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const DEFAULT_RANGE_DAYS: i64 = 30;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Interval {
+    Day,
+    /// Weeks start on Monday
+    Week,
+}
+
+impl Interval {
+    fn parse(interval: &str) -> Option<Interval> {
+        match interval {
+            "day" => Some(Interval::Day),
+            "week" => Some(Interval::Week),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Interval::Day => "day",
+            Interval::Week => "week",
+        }
+    }
+
+    /// The first day of the bucket `day` falls in
+    fn bucket_start(&self, day: NaiveDate) -> NaiveDate {
+        match self {
+            Interval::Day => day,
+            Interval::Week => day - Duration::days(day.weekday().num_days_from_monday() as i64),
+        }
+    }
+
+    /// Widen `from` to `to` to whole buckets, so none of them counts only
+    /// part of its days
+    fn cover(&self, from: NaiveDate, to: NaiveDate) -> (NaiveDate, NaiveDate) {
+        let to = self.bucket_start(to) + self.step() - Duration::days(1);
+        (self.bucket_start(from), to)
+    }
+
+    fn step(&self) -> Duration {
+        match self {
+            Interval::Day => Duration::days(1),
+            Interval::Week => Duration::weeks(1),
+        }
+    }
+}
+
+/// Whose killmails a time series counts
+#[derive(Debug, Clone, Copy)]
+pub enum TimeseriesSubject {
+    Character(u64),
+    Organization(OrganizationKind, u64),
+}
+
+impl TimeseriesSubject {
+    fn column_name(&self) -> &'static str {
+        match self {
+            TimeseriesSubject::Character(_) => "character_id",
+            TimeseriesSubject::Organization(kind, _) => kind.column_name(),
+        }
+    }
+
+    fn id(&self) -> u64 {
+        match self {
+            TimeseriesSubject::Character(id) => *id,
+            TimeseriesSubject::Organization(_, id) => *id,
+        }
+    }
+
+    /// Identifies the subject in cache keys
+    pub fn key(&self) -> String {
+        match self {
+            TimeseriesSubject::Character(id) => format!("character:{}", id),
+            TimeseriesSubject::Organization(kind, id) => format!("{}:{}", kind.name(), id),
+        }
+    }
+}
+
+/// Days `from` to `to` inclusive, in buckets of `interval`. Both ends fall on
+/// bucket boundaries.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct TimeseriesRange {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub interval: Interval,
+}
+
+impl TimeseriesRange {
+    /// Build a range from query parameters. Missing dates default to the last
+    /// 30 days, the interval defaults to days. Weekly ranges are widened to
+    /// whole weeks.
+    pub fn parse(
+        from: Option<&str>,
+        to: Option<&str>,
+        interval: Option<&str>,
+    ) -> Result<TimeseriesRange, String> {
+        let parse_date = |date: &str| {
+            NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map_err(|_| format!("Couldn't parse date {}, expected YYYY-MM-DD", date))
+        };
+        let to = match to {
+            Some(to) => parse_date(to)?,
+            None => Utc::now().naive_utc().date(),
+        };
+        let from = match from {
+            Some(from) => parse_date(from)?,
+            None => to - Duration::days(DEFAULT_RANGE_DAYS - 1),
+        };
+        let interval = match interval {
+            Some(interval) => Interval::parse(interval)
+                .ok_or_else(|| format!("Unknown interval {}, expected day or week", interval))?,
+            None => Interval::Day,
+        };
+        if from > to {
+            return Err(format!("from ({}) is after to ({})", from, to));
+        }
+        let max_days = config::get().stats.timeseries_max_days;
+        if (to - from).num_days() + 1 > max_days {
+            return Err(format!("Ranges can cover at most {} days", max_days));
+        }
+        let (from, to) = interval.cover(from, to);
+        Ok(TimeseriesRange { from, to, interval })
+    }
+
+    /// Identifies the range in cache keys
+    pub fn key(&self) -> String {
+        format!("{}:{}:{}", self.from, self.to, self.interval.name())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct TimeseriesPoint {
+    pub start: NaiveDate,
+    pub kills: usize,
+    pub losses: usize,
+    pub solo_kills: usize,
+    pub solo_losses: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct Timeseries {
+    pub range: TimeseriesRange,
+    pub points: Vec<TimeseriesPoint>,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct DailyCount {
+    day: NaiveDate,
+    count: i64,
+    solo: i64,
+}
+
+/// Killmails per day with the subject on `table` (attackers or victims), and
+/// how many of them had a single player attacker
+async fn get_daily_counts(
+    db: &DatabaseConnection,
+    subject: TimeseriesSubject,
+    range: &TimeseriesRange,
+    table: &str,
+) -> Result<Vec<DailyCount>, DbErr> {
+    let sql = format!(
+        r#"SELECT DATE(k.killmail_time) AS day, COUNT(*) AS count,
+            CAST(COALESCE(SUM((
+                SELECT COUNT(p.character_id) FROM attackers p WHERE p.killmail_id = k.killmail_id
+            ) = 1), 0) AS SIGNED) AS solo
+        FROM killmails k
+        WHERE k.killmail_id IN (SELECT killmail_id FROM {table} WHERE {column} = ?)
+            AND k.killmail_time >= ? AND k.killmail_time < ?
+        GROUP BY day"#,
+        table = table,
+        column = subject.column_name()
+    );
+    let values: Vec<Value> = vec![
+        subject.id().into(),
+        range.from.and_hms(0, 0, 0).into(),
+        (range.to + Duration::days(1)).and_hms(0, 0, 0).into(),
+    ];
+    DailyCount::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::MySql,
+        &sql,
+        values,
+    ))
+    .all(db)
+    .await
+}
+
+/// Kills, losses and solo counts for each day or week in `range`, including
+/// the ones with nothing in them
+pub async fn get_timeseries(
+    db: &DatabaseConnection,
+    subject: TimeseriesSubject,
+    range: &TimeseriesRange,
+) -> Result<Timeseries, DbErr> {
+    let mut points: BTreeMap<NaiveDate, TimeseriesPoint> = BTreeMap::new();
+    let mut start = range.from;
+    while start <= range.to {
+        points.insert(
+            start,
+            TimeseriesPoint {
+                start,
+                kills: 0,
+                losses: 0,
+                solo_kills: 0,
+                solo_losses: 0,
+            },
+        );
+        start += range.interval.step();
+    }
+    for count in get_daily_counts(db, subject, range, "attackers").await? {
+        if let Some(point) = points.get_mut(&range.interval.bucket_start(count.day)) {
+            point.kills += count.count as usize;
+            point.solo_kills += count.solo as usize;
+        }
+    }
+    for count in get_daily_counts(db, subject, range, "victims").await? {
+        if let Some(point) = points.get_mut(&range.interval.bucket_start(count.day)) {
+            point.losses += count.count as usize;
+            point.solo_losses += count.solo as usize;
+        }
+    }
+    Ok(Timeseries {
+        range: range.clone(),
+        points: points.into_values().collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(day, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn weeks_start_on_monday() {
+        // 2022-03-07 is a Monday
+        assert_eq!(
+            Interval::Week.bucket_start(date("2022-03-07")),
+            date("2022-03-07")
+        );
+        assert_eq!(
+            Interval::Week.bucket_start(date("2022-03-13")),
+            date("2022-03-07")
+        );
+        assert_eq!(
+            Interval::Week.bucket_start(date("2022-03-14")),
+            date("2022-03-14")
+        );
+        assert_eq!(
+            Interval::Day.bucket_start(date("2022-03-13")),
+            date("2022-03-13")
+        );
+    }
+
+    #[test]
+    fn weekly_ranges_cover_whole_weeks() {
+        assert_eq!(
+            Interval::Week.cover(date("2022-03-09"), date("2022-03-15")),
+            (date("2022-03-07"), date("2022-03-20"))
+        );
+        assert_eq!(
+            Interval::Week.cover(date("2022-03-07"), date("2022-03-13")),
+            (date("2022-03-07"), date("2022-03-13"))
+        );
+        assert_eq!(
+            Interval::Day.cover(date("2022-03-09"), date("2022-03-15")),
+            (date("2022-03-09"), date("2022-03-15"))
+        );
+    }
+
+    #[test]
+    fn parse_rejects_from_after_to() {
+        assert!(TimeseriesRange::parse(Some("2022-03-10"), Some("2022-03-09"), None).is_err());
+        assert!(
+            TimeseriesRange::parse(Some("2022-03-10"), Some("2022-03-09"), Some("week")).is_err()
+        );
+    }
+
+    #[test]
+    fn parse_rejects_bad_input() {
+        assert!(TimeseriesRange::parse(Some("10/03/2022"), Some("2022-03-09"), None).is_err());
+        assert!(TimeseriesRange::parse(None, Some("2022-03-09"), Some("month")).is_err());
+    }
+}
@@ -0,0 +1,281 @@
+use crate::config::StatsWindow;
+use crate::entity::prelude::*;
+use crate::entity::*;
+use crate::esi;
+use crate::esi::EsiError;
+use crate::stats_processing;
+use crate::stats_processing::push_window_filter;
+use chrono::Utc;
+use schemars::JsonSchema;
+use sea_orm::prelude::*;
+use sea_orm::{
+    ConnectionTrait, DatabaseConnection, DbBackend, DbErr, FromQueryResult, QueryOrder,
+    QuerySelect, Statement, Value,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const PRICE_INSERT_CHUNK_SIZE: usize = 1000;
+const VALUATION_CHUNK_SIZE: usize = 500;
+/// Blueprint copies show up on killmails with this singleton value. They
+/// can't be sold so they're worth nothing.
+const BLUEPRINT_COPY_SINGLETON: u64 = 2;
+
+#[derive(Debug)]
+pub enum ValuationError {
+    ESIError(EsiError),
+    DBError(DbErr),
+}
+
+impl From<EsiError> for ValuationError {
+    fn from(err: EsiError) -> ValuationError {
+        ValuationError::ESIError(err)
+    }
+}
+
+impl From<DbErr> for ValuationError {
+    fn from(err: DbErr) -> ValuationError {
+        ValuationError::DBError(err)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+pub struct IskStats {
+    pub destroyed: f64,
+    pub lost: f64,
+    /// Share of all ISK involved that was the other side's, from 0 to 1
+    pub efficiency: Option<f64>,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct LatestPrice {
+    type_id: u64,
+    adjusted_price: Option<f64>,
+    average_price: Option<f64>,
+}
+
+/// Fetch the current market prices from ESI and store them as a new snapshot,
+/// keeping the old ones so valuations can be compared or redone later
+pub async fn harvest_prices(db: &DatabaseConnection) -> Result<usize, ValuationError> {
+    let prices = esi::get_market_prices().await?;
+    let observed_at = Utc::now().naive_utc();
+    let price_count = prices.len();
+    for chunk in prices.chunks(PRICE_INSERT_CHUNK_SIZE) {
+        MarketPrices::insert_many(
+            chunk
+                .iter()
+                .cloned()
+                .map(|price| market_prices::ActiveModel::from_esi(price, observed_at)),
+        )
+        .exec(db)
+        .await?;
+    }
+    info!("Stored {} market prices", price_count);
+    Ok(price_count)
+}
+
+/// The most recent price of each type. The average price is preferred as it
+/// tracks what things actually sell for, the adjusted price is a fallback.
+pub async fn get_latest_prices(
+    db: &DatabaseConnection,
+    type_ids: &[u64],
+) -> Result<HashMap<u64, f64>, DbErr> {
+    if type_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let placeholders = vec!["?"; type_ids.len()].join(", ");
+    let sql = format!(
+        r#"SELECT p.type_id, p.adjusted_price, p.average_price
+        FROM market_prices p
+        JOIN (
+            SELECT type_id, MAX(observed_at) AS observed_at
+            FROM market_prices
+            WHERE type_id IN ({})
+            GROUP BY type_id
+        ) latest ON latest.type_id = p.type_id AND latest.observed_at = p.observed_at"#,
+        placeholders
+    );
+    let values: Vec<Value> = type_ids.iter().map(|type_id| (*type_id).into()).collect();
+    let prices = LatestPrice::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::MySql,
+        &sql,
+        values,
+    ))
+    .all(db)
+    .await?;
+    Ok(prices
+        .into_iter()
+        .filter_map(|price| {
+            price
+                .average_price
+                .or(price.adjusted_price)
+                .map(|value| (price.type_id, value))
+        })
+        .collect())
+}
+
+async fn value_killmail_chunk(db: &DatabaseConnection, killmail_ids: &[u64]) -> Result<(), DbErr> {
+    let victims = Victims::find()
+        .filter(victims::Column::KillmailId.is_in(killmail_ids.to_vec()))
+        .all(db)
+        .await?;
+    let mut items: HashMap<u64, Vec<victim_items::Model>> = HashMap::new();
+    for item in VictimItems::find()
+        .filter(victim_items::Column::KillmailId.is_in(killmail_ids.to_vec()))
+        .all(db)
+        .await?
+    {
+        items.entry(item.killmail_id).or_default().push(item);
+    }
+    let mut type_ids: Vec<u64> = victims
+        .iter()
+        .map(|victim| victim.ship_type_id)
+        .chain(items.values().flatten().map(|item| item.item_type_id))
+        .collect();
+    type_ids.sort_unstable();
+    type_ids.dedup();
+    let prices = get_latest_prices(db, &type_ids).await?;
+    let price_of = |type_id: u64| prices.get(&type_id).cloned().unwrap_or(0.0);
+    let valued_at = Utc::now().naive_utc();
+    let mut rows: Vec<String> = Vec::with_capacity(victims.len());
+    let mut values: Vec<Value> = Vec::with_capacity(victims.len() * 6);
+    for victim in victims.iter() {
+        let ship_value = price_of(victim.ship_type_id);
+        let mut destroyed_value = 0.0;
+        let mut dropped_value = 0.0;
+        for item in items.get(&victim.killmail_id).into_iter().flatten() {
+            if item.singleton == BLUEPRINT_COPY_SINGLETON {
+                continue;
+            }
+            let price = price_of(item.item_type_id);
+            destroyed_value += price * item.quantity_destroyed as f64;
+            dropped_value += price * item.quantity_dropped as f64;
+        }
+        rows.push("(?, ?, ?, ?, ?, ?)".to_string());
+        values.push(victim.killmail_id.into());
+        values.push(ship_value.into());
+        values.push(destroyed_value.into());
+        values.push(dropped_value.into());
+        values.push((ship_value + destroyed_value + dropped_value).into());
+        values.push(valued_at.into());
+    }
+    if rows.is_empty() {
+        return Ok(());
+    }
+    let sql = format!(
+        r#"INSERT INTO killmail_values
+            (killmail_id, ship_value, destroyed_value, dropped_value, total_value, valued_at)
+        VALUES {}
+        ON DUPLICATE KEY UPDATE
+            ship_value = VALUES(ship_value),
+            destroyed_value = VALUES(destroyed_value),
+            dropped_value = VALUES(dropped_value),
+            total_value = VALUES(total_value),
+            valued_at = VALUES(valued_at)"#,
+        rows.join(", ")
+    );
+    db.execute(Statement::from_sql_and_values(
+        DbBackend::MySql,
+        &sql,
+        values,
+    ))
+    .await?;
+    Ok(())
+}
+
+/// Value killmails from their victim's ship and items at the latest prices,
+/// replacing any earlier valuation. Killmails stored before items were kept
+/// are valued by the ship alone.
+pub async fn value_killmails(db: &DatabaseConnection, killmail_ids: &[u64]) -> Result<(), DbErr> {
+    for chunk in killmail_ids.chunks(VALUATION_CHUNK_SIZE) {
+        value_killmail_chunk(db, chunk).await?;
+    }
+    Ok(())
+}
+
+/// Value every killmail again, e.g. after fetching new prices. Returns the
+/// number of killmails valued.
+pub async fn revalue_all(db: &DatabaseConnection) -> Result<usize, DbErr> {
+    let mut last_killmail_id: u64 = 0;
+    let mut valued: usize = 0;
+    loop {
+        let killmail_ids: Vec<u64> = Killmails::find()
+            .filter(killmails::Column::KillmailId.gt(last_killmail_id))
+            .order_by_asc(killmails::Column::KillmailId)
+            .limit(VALUATION_CHUNK_SIZE as u64)
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|killmail| killmail.killmail_id)
+            .collect();
+        match killmail_ids.last() {
+            Some(killmail_id) => last_killmail_id = *killmail_id,
+            None => break,
+        }
+        value_killmail_chunk(db, &killmail_ids).await?;
+        valued += killmail_ids.len();
+        info!("Valued {} killmails", valued);
+    }
+    Ok(valued)
+}
+
+#[derive(Debug, FromQueryResult)]
+struct CharacterIskTotals {
+    character_id: u64,
+    destroyed: f64,
+    lost: f64,
+}
+
+/// ISK destroyed on each character's kills and lost on their losses inside
+/// `window`. Kills count in full for everyone on them, as zKillboard does.
+pub async fn get_isk_stats(
+    db: &DatabaseConnection,
+    character_ids: &[u64],
+    window: &StatsWindow,
+) -> Result<HashMap<u64, IskStats>, DbErr> {
+    if character_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let mut values: Vec<Value> = Vec::new();
+    let mut sql = format!(
+        r#"SELECT c.character_id AS character_id,
+            CAST(COALESCE(SUM(CASE WHEN c.is_loss = 0 THEN kv.total_value END), 0) AS DOUBLE)
+                AS destroyed,
+            CAST(COALESCE(SUM(CASE WHEN c.is_loss = 1 THEN kv.total_value END), 0) AS DOUBLE)
+                AS lost
+        FROM ({}) c
+        JOIN killmails k ON k.killmail_id = c.killmail_id
+        JOIN killmail_values kv ON kv.killmail_id = c.killmail_id
+        WHERE 1 = 1"#,
+        stats_processing::character_killmails_sql(&mut values, character_ids)
+    );
+    push_window_filter(&mut sql, &mut values, window);
+    sql.push_str(" GROUP BY c.character_id");
+    let mut totals: HashMap<u64, (f64, f64)> = CharacterIskTotals::find_by_statement(
+        Statement::from_sql_and_values(DbBackend::MySql, &sql, values),
+    )
+    .all(db)
+    .await?
+    .into_iter()
+    .map(|totals| (totals.character_id, (totals.destroyed, totals.lost)))
+    .collect();
+    Ok(character_ids
+        .iter()
+        .map(|character_id| {
+            let (destroyed, lost) = totals.remove(character_id).unwrap_or((0.0, 0.0));
+            let efficiency = if destroyed + lost > 0.0 {
+                Some(destroyed / (destroyed + lost))
+            } else {
+                None
+            };
+            (
+                *character_id,
+                IskStats {
+                    destroyed,
+                    lost,
+                    efficiency,
+                },
+            )
+        })
+        .collect())
+}
@@ -1,11 +1,10 @@
+use crate::config;
 use datamodels::esi_models::ESIKillmailRequest;
 use chrono::{Duration, NaiveDate};
 use futures::{stream, StreamExt};
 use serde_json::{Map, Value};
 use std::convert::TryInto;
 
-const ZKILL_URL: &str = "https://zkillboard.com/api";
-
 #[derive(Debug)]
 pub enum ZKillError {
     APIError(reqwest::Error),
@@ -25,7 +24,7 @@ impl From<serde_json::Error> for ZKillError {
 }
 
 fn get_url(path: String) -> String {
-    format!("{}/{}", ZKILL_URL, path)
+    format!("{}/{}", config::get().zkill.url, path)
 }
 
 pub async fn get_killmail_requests_from_date(
@@ -51,7 +50,7 @@ pub async fn get_history_records(dates: Vec<NaiveDate>) -> Vec<ESIKillmailReques
     let mut records: Vec<ESIKillmailRequest> = Vec::new();
     let mut bodies = stream::iter(dates)
         .map(|date| async move { get_killmail_requests_from_date(date).await })
-        .buffer_unordered(10);
+        .buffer_unordered(config::get().zkill.history_concurrency);
     while let Some(record) = bodies.next().await {
         match record {
             Ok(rec) => {
@@ -81,6 +81,20 @@ pub struct ESIKillmail {
     pub solar_system_id: u64,
     pub victim: ESIVictim,
     pub attackers: Vec<ESIAttacker>,
+    /// Only present on killmails that came through zKillboard
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zkb: Option<ZKBInfo>,
+}
+
+/// zKillboard's own metadata, sent alongside killmails on the websocket
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ZKBInfo {
+    pub hash: Option<String>,
+    pub total_value: Option<f64>,
+    pub npc: bool,
+    pub solo: bool,
+    pub awox: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -92,6 +106,27 @@ pub struct ESIVictim {
     pub damage_taken: u64,
     pub ship_type_id: u64,
     pub position: Option<ESIKillPosition>,
+    #[serde(default)]
+    pub items: Vec<ESIItem>,
+}
+
+/// An item fitted to or carried by the victim. Containers list their
+/// contents in `items`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ESIItem {
+    pub item_type_id: u64,
+    pub flag: u64,
+    pub singleton: u64,
+    pub quantity_destroyed: Option<u64>,
+    pub quantity_dropped: Option<u64>,
+    pub items: Option<Vec<ESIItem>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ESIMarketPrice {
+    pub type_id: u64,
+    pub adjusted_price: Option<f64>,
+    pub average_price: Option<f64>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -114,6 +149,29 @@ pub struct ESIKillPosition {
     pub z: f64,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ESIRegion {
+    pub region_id: u64,
+    pub name: String,
+    pub constellations: Vec<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ESIConstellation {
+    pub constellation_id: u64,
+    pub name: String,
+    pub region_id: u64,
+    pub systems: Vec<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ESISolarSystem {
+    pub system_id: u64,
+    pub name: String,
+    pub constellation_id: u64,
+    pub security_status: f64,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ESIFaction {
     pub faction_id: u64,
@@ -9,8 +9,6 @@ use druid::{
     AppDelegate, AppLauncher, Color, Command, Data, DelegateCtx, Env, Handled, Lens, Selector,
     Target, Widget, WidgetExt, WindowDesc,
 };
-use futures::{stream, StreamExt};
-use reqwest::Response;
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::unbounded_channel;
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
@@ -51,6 +49,23 @@ pub struct CharacterStats {
     pub solo_kill_loss_ratio: KillLossRatio,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LookupStatus {
+    Found,
+    NotFound,
+    Error,
+}
+
+/// One line of the bulk stats response
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CharacterStatsResult {
+    pub name: String,
+    pub status: LookupStatus,
+    pub stats: Option<CharacterStats>,
+    pub error: Option<String>,
+}
+
 #[derive(Clone, Data, Lens, Debug)]
 pub struct Character {
     pub name: String,
@@ -232,105 +247,118 @@ fn get_characters_from_clipboard(clipboard: &String) -> Vector<Character> {
     characters
 }
 
-fn get_character_stats_url(name: &str) -> String {
-    format!("{}/character_stats/{}", JAGER_URL, name)
+fn get_bulk_stats_url() -> String {
+    format!("{}/v1/character_stats", JAGER_URL)
 }
 
-async fn deserialize_stats_result(character: Character, response: Response) -> Character {
-    let stats_result = response.json::<CharacterStats>().await;
-    match stats_result {
-        Ok(stats) => {
-            info!("Fetched character info for {}", character.name);
+fn failed_character(name: String) -> Character {
+    Character {
+        name,
+        valid: false,
+        in_progress: false,
+        is_found: true,
+        stats: None,
+    }
+}
+
+fn get_character_from_result(name: String, result: CharacterStatsResult) -> Character {
+    match result.status {
+        LookupStatus::Found => {
+            info!("Fetched character info for {}", name);
             Character {
-                name: character.name,
+                name,
                 valid: true,
                 in_progress: false,
                 is_found: true,
-                stats: Some(stats),
+                stats: result.stats,
             }
         }
-        Err(e) => {
+        LookupStatus::NotFound => Character {
+            name,
+            valid: true,
+            in_progress: false,
+            is_found: false,
+            stats: None,
+        },
+        LookupStatus::Error => {
             error!(
-                "Character deserialize failed for {}: {:?}",
-                character.name, e
+                "Server failed to process {}: {}",
+                name,
+                result.error.unwrap_or_default()
             );
-            Character {
-                name: character.name,
-                valid: false,
-                in_progress: false,
-                is_found: true,
-                stats: None,
-            }
+            failed_character(name)
         }
     }
 }
 
-async fn get_character_stats(character: Character) -> Character {
-    let request_url = get_character_stats_url(&character.name);
-    info!("Sending request to {}", request_url);
-    match reqwest::get(request_url).await {
-        Ok(response) => match response.error_for_status() {
-            Ok(message) => deserialize_stats_result(character, message).await,
-            Err(e) => {
-                if let Some(code) = e.status() {
-                    match code {
-                        reqwest::StatusCode::NOT_FOUND => Character {
-                            name: character.name,
-                            valid: true,
-                            in_progress: false,
-                            is_found: false,
-                            stats: None,
-                        },
-                        _ => {
-                            error!(
-                                "Got {} from server while processing {}",
-                                code, character.name
-                            );
-                            Character {
-                                name: character.name,
-                                valid: false,
-                                in_progress: false,
-                                is_found: true,
-                                stats: None,
-                            }
-                        }
-                    }
-                } else {
-                    error!("Unknown error occured processing {}", character.name);
-                    Character {
-                        name: character.name,
-                        valid: false,
-                        in_progress: false,
-                        is_found: true,
-                        stats: None,
-                    }
-                }
-            }
-        },
+/// Send an update for every pending character the result line is for. The
+/// server trims names and drops duplicates that differ only in case.
+fn handle_result_line(
+    line: &[u8],
+    pending: &mut Vec<Character>,
+    tx_pipe: &UnboundedSender<Character>,
+) {
+    let result = match serde_json::from_slice::<CharacterStatsResult>(line) {
+        Ok(result) => result,
         Err(e) => {
-            error!("Cannot fetch character {}: {:?}", character.name, e);
-            Character {
-                name: character.name,
-                valid: false,
-                in_progress: false,
-                is_found: true,
-                stats: None,
-            }
+            error!("Character deserialize failed: {:?}", e);
+            return;
+        }
+    };
+    let (matching, rest): (Vec<Character>, Vec<Character>) = pending
+        .drain(..)
+        .partition(|character| character.name.trim().eq_ignore_ascii_case(&result.name));
+    *pending = rest;
+    for character in matching {
+        tx_pipe
+            .send(get_character_from_result(character.name, result.clone()))
+            .unwrap();
+    }
+}
+
+async fn stream_characters_stats(
+    pending: &mut Vec<Character>,
+    tx_pipe: &UnboundedSender<Character>,
+) -> Result<(), reqwest::Error> {
+    let names: Vec<String> = pending
+        .iter()
+        .map(|character| character.name.clone())
+        .collect();
+    let request_url = get_bulk_stats_url();
+    info!(
+        "Sending request for {} names to {}",
+        names.len(),
+        request_url
+    );
+    let mut response = reqwest::Client::new()
+        .post(request_url)
+        .json(&names)
+        .send()
+        .await?
+        .error_for_status()?;
+    let mut buffer: Vec<u8> = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        buffer.extend_from_slice(&chunk);
+        while let Some(line_end) = buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=line_end).collect();
+            handle_result_line(&line, pending, tx_pipe);
         }
     }
+    if !buffer.is_empty() {
+        handle_result_line(&buffer, pending, tx_pipe);
+    }
+    Ok(())
 }
 
 async fn get_characters_stats(characters: Vector<Character>, tx_pipe: UnboundedSender<Character>) {
     info!("Starting character info fetch");
-    let mut bodies = stream::iter(characters)
-        .map(|character| async move {
-            info!("Fetching for character {}", character.name);
-            let character_state = get_character_stats(character).await;
-            character_state
-        })
-        .buffer_unordered(10);
-    while let Some(item) = bodies.next().await {
-        tx_pipe.send(item).unwrap();
+    let mut pending: Vec<Character> = characters.into_iter().collect();
+    if let Err(e) = stream_characters_stats(&mut pending, &tx_pipe).await {
+        error!("Cannot fetch character stats: {:?}", e);
+    }
+    // Anything the server didn't answer for is shown as an error
+    for character in pending {
+        tx_pipe.send(failed_character(character.name)).unwrap();
     }
 }
 
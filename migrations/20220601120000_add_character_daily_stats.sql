@@ -0,0 +1,11 @@
+CREATE TABLE character_daily_stats (
+    character_id BIGINT unsigned NOT NULL,
+    day DATE NOT NULL,
+    kills BIGINT unsigned NOT NULL DEFAULT 0,
+    losses BIGINT unsigned NOT NULL DEFAULT 0,
+    solo_kills BIGINT unsigned NOT NULL DEFAULT 0,
+    solo_losses BIGINT unsigned NOT NULL DEFAULT 0,
+    final_blows BIGINT unsigned NOT NULL DEFAULT 0,
+    PRIMARY KEY (character_id, day),
+    FOREIGN KEY (character_id) REFERENCES character_public_info (character_id) ON DELETE CASCADE
+);
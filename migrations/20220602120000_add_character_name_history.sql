@@ -0,0 +1,14 @@
+ALTER TABLE character_public_info DROP INDEX character_name;
+-- utf8mb4_general_ci makes lookups on character_name case-insensitive
+CREATE TABLE character_name_history (
+    history_id BIGINT unsigned PRIMARY KEY UNIQUE NOT NULL AUTO_INCREMENT,
+    character_id BIGINT unsigned NOT NULL,
+    character_name VARCHAR(255) NOT NULL COLLATE utf8mb4_general_ci,
+    observed_from DATETIME NOT NULL,
+    observed_to DATETIME,
+    INDEX (character_name, observed_to),
+    FOREIGN KEY (character_id) REFERENCES character_public_info (character_id) ON DELETE CASCADE
+);
+INSERT INTO character_name_history (character_id, character_name, observed_from)
+SELECT character_id, character_name, COALESCE(last_updated, NOW())
+FROM character_public_info;
@@ -0,0 +1,16 @@
+ALTER TABLE killmails ADD COLUMN awox BOOLEAN NOT NULL DEFAULT FALSE;
+-- A killmail is an awox when a player attacker shares the victim's player
+-- corporation (ids 1000000 to 1999999 are NPC corporations) or alliance
+UPDATE killmails k
+JOIN victims v ON v.killmail_id = k.killmail_id
+SET k.awox = TRUE
+WHERE EXISTS (
+    SELECT 1 FROM attackers a
+    WHERE a.killmail_id = k.killmail_id
+        AND a.character_id IS NOT NULL
+        AND (v.character_id IS NULL OR a.character_id <> v.character_id)
+        AND (
+            (a.corporation_id = v.corporation_id AND v.corporation_id NOT BETWEEN 1000000 AND 1999999)
+            OR a.alliance_id = v.alliance_id
+        )
+);
@@ -0,0 +1,28 @@
+-- Every fetch of /markets/prices is kept so killmails can be revalued later
+CREATE TABLE market_prices (
+    price_id BIGINT unsigned PRIMARY KEY UNIQUE NOT NULL AUTO_INCREMENT,
+    type_id BIGINT unsigned NOT NULL,
+    adjusted_price DOUBLE,
+    average_price DOUBLE,
+    observed_at DATETIME NOT NULL,
+    INDEX (type_id, observed_at)
+);
+CREATE TABLE victim_items (
+    victim_item_id BIGINT unsigned PRIMARY KEY UNIQUE NOT NULL AUTO_INCREMENT,
+    killmail_id BIGINT unsigned NOT NULL,
+    item_type_id BIGINT unsigned NOT NULL,
+    flag BIGINT unsigned NOT NULL,
+    singleton BIGINT unsigned NOT NULL,
+    quantity_destroyed BIGINT unsigned NOT NULL DEFAULT 0,
+    quantity_dropped BIGINT unsigned NOT NULL DEFAULT 0,
+    FOREIGN KEY (killmail_id) REFERENCES killmails (killmail_id) ON DELETE CASCADE
+);
+CREATE TABLE killmail_values (
+    killmail_id BIGINT unsigned PRIMARY KEY NOT NULL,
+    ship_value DOUBLE NOT NULL,
+    destroyed_value DOUBLE NOT NULL,
+    dropped_value DOUBLE NOT NULL,
+    total_value DOUBLE NOT NULL,
+    valued_at DATETIME NOT NULL,
+    FOREIGN KEY (killmail_id) REFERENCES killmails (killmail_id) ON DELETE CASCADE
+);
@@ -0,0 +1,17 @@
+CREATE TABLE regions (
+    region_id BIGINT unsigned PRIMARY KEY UNIQUE NOT NULL,
+    name VARCHAR(255) NOT NULL
+);
+CREATE TABLE constellations (
+    constellation_id BIGINT unsigned PRIMARY KEY UNIQUE NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    region_id BIGINT unsigned NOT NULL,
+    FOREIGN KEY (region_id) REFERENCES regions (region_id) ON DELETE CASCADE
+);
+CREATE TABLE solar_systems (
+    system_id BIGINT unsigned PRIMARY KEY UNIQUE NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    constellation_id BIGINT unsigned NOT NULL,
+    security_status DOUBLE NOT NULL,
+    FOREIGN KEY (constellation_id) REFERENCES constellations (constellation_id) ON DELETE CASCADE
+);
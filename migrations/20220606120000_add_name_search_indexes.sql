@@ -0,0 +1,8 @@
+-- Name search matches prefixes with LIKE 'abc%'. The names are TEXT, which
+-- MySQL can only index by a prefix of the value.
+ALTER TABLE character_public_info ADD INDEX (character_name(64));
+ALTER TABLE corporations ADD INDEX (name(64)),
+    ADD INDEX (ticker(5));
+ALTER TABLE alliances ADD INDEX (name(64)),
+    ADD INDEX (ticker(5));
+ALTER TABLE esi_types ADD INDEX (type_name(64));
@@ -0,0 +1,8 @@
+-- Fuzzy name search looks for names sharing letter pairs with the query. The
+-- ngram parser splits names into pairs regardless of language or spaces.
+-- It ships with MySQL 5.7.6 and newer only. On MariaDB skip this migration,
+-- fuzzy search then matches names containing the query instead.
+ALTER TABLE character_public_info ADD FULLTEXT INDEX (character_name) WITH PARSER ngram;
+ALTER TABLE corporations ADD FULLTEXT INDEX (name) WITH PARSER ngram;
+ALTER TABLE alliances ADD FULLTEXT INDEX (name) WITH PARSER ngram;
+ALTER TABLE esi_types ADD FULLTEXT INDEX (type_name) WITH PARSER ngram;
@@ -0,0 +1,14 @@
+-- Keep only the newest open observation of each character, then allow just
+-- one from now on. Unique indexes ignore NULLs, so the generated column is
+-- only set on open observations.
+UPDATE character_name_history h
+JOIN character_name_history newer
+    ON newer.character_id = h.character_id
+    AND newer.observed_to IS NULL
+    AND newer.history_id > h.history_id
+SET h.observed_to = newer.observed_from
+WHERE h.observed_to IS NULL;
+ALTER TABLE character_name_history
+    ADD COLUMN open_character_id BIGINT unsigned
+        AS (IF(observed_to IS NULL, character_id, NULL)) STORED,
+    ADD UNIQUE INDEX open_observation (open_character_id);